@@ -29,5 +29,6 @@ fn main() {
     #[cfg(target_os = "windows")]
     require_admin();
 
+    #[cfg(feature = "gui")]
     slint_build::compile("ui/main-ui.slint").unwrap();
 }
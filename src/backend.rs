@@ -0,0 +1,52 @@
+//! Where the adapters are read from and where the settings are applied.
+//!
+//! The app uses `System`, the tests use a fake that records the applies, see `test_support`.
+
+use crate::binding::{self, Hardware};
+use crate::set_ip;
+use crate::Result;
+use net_adapters::adapter::Nic;
+use std::collections::HashMap;
+
+/// A query of the cards of the adapters keyed by adapter name, it may run on another thread
+pub type HardwareQuery = Box<dyn FnOnce() -> HashMap<String, Hardware> + Send>;
+
+pub trait Backend {
+    /// the adapters of the machine
+    fn adapters(&self) -> Vec<Nic>;
+
+    /// apply the settings of `nic` to the adapter named `nic.name()`
+    fn apply(&self, nic: &Nic) -> Result<String>;
+
+    /// put back settings read from the adapter before an apply, for undo and the rollback of a
+    /// scene. Static settings without any address remove the addresses of the adapter
+    fn restore(&self, nic: &Nic) -> Result<String>;
+
+    /// the query of the cards of the adapters. It takes a while on the machine, so the window
+    /// runs it on another thread. No card is known by default
+    fn hardware(&self) -> HardwareQuery {
+        Box::new(HashMap::new)
+    }
+}
+
+/// The adapters of this machine, the settings are applied by `netsh`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct System;
+
+impl Backend for System {
+    fn adapters(&self) -> Vec<Nic> {
+        net_adapters::adapter::get_adapters()
+    }
+
+    fn apply(&self, nic: &Nic) -> Result<String> {
+        set_ip::apply(nic)
+    }
+
+    fn restore(&self, nic: &Nic) -> Result<String> {
+        set_ip::restore(nic)
+    }
+
+    fn hardware(&self) -> HardwareQuery {
+        Box::new(|| binding::get_adapter_hardware().unwrap_or_default())
+    }
+}
@@ -0,0 +1,381 @@
+use crate::media::MediaType;
+use crate::{Error, Result};
+use encoding::{all::GB18030, DecoderTrap, Encoding};
+use net_adapters::adapter::Nic;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Command;
+
+/// The rules to find the adapter of a saved profile on the current machine.
+/// The guid of the adapter is tried first, then the MAC address, then the name pattern.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AdapterBinding {
+    #[serde(default)]
+    pub mac: Option<String>,
+    /// adapter name, `*` and `?` can be used as wildcards, case insensitive
+    pub name_pattern: String,
+}
+
+/// How a profile was matched to an adapter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchedBy {
+    Guid,
+    Mac,
+    Name,
+}
+
+/// An adapter of the current machine, with the fields used for matching
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdapterIdentity {
+    pub guid: String,
+    pub name: String,
+    pub index: u32,
+    pub mac: Option<String>,
+    /// `None` if the card is not known
+    pub hardware: Option<Hardware>,
+}
+
+impl AdapterBinding {
+    pub fn new(name: &str, mac: Option<String>) -> Self {
+        AdapterBinding {
+            mac: mac.map(|mac| normalize_mac(&mac)),
+            name_pattern: name.to_string(),
+        }
+    }
+
+    /// find the adapter for a profile saved from the adapter `guid`
+    pub fn resolve<'a>(
+        &self,
+        guid: &str,
+        adapters: &'a [AdapterIdentity],
+    ) -> Option<(&'a AdapterIdentity, MatchedBy)> {
+        if let Some(item) = adapters.iter().find(|item| item.guid == guid) {
+            return Some((item, MatchedBy::Guid));
+        }
+
+        if let Some(mac) = &self.mac {
+            if let Some(item) = adapters.iter().find(|item| item.mac.as_ref() == Some(mac)) {
+                return Some((item, MatchedBy::Mac));
+            }
+        }
+
+        adapters
+            .iter()
+            .find(|item| match_pattern(&self.name_pattern, &item.name))
+            .map(|item| (item, MatchedBy::Name))
+    }
+}
+
+/// case insensitive wildcard match, `*` matches any sequence and `?` matches one char
+fn match_pattern(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            backtrack = Some((star_p, star_t + 1));
+            p = star_p + 1;
+            t = star_t + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn normalize_mac(mac: &str) -> String {
+    mac.trim().to_uppercase().replace(':', "-")
+}
+
+/// collect the identities of `adapters` with their cards in `hardware`, see `Backend::hardware`.
+/// The adapters without a known card can still be matched by guid and name
+pub fn identify(adapters: &[Nic], hardware: &HashMap<String, Hardware>) -> Vec<AdapterIdentity> {
+    adapters
+        .iter()
+        .map(|nic| AdapterIdentity {
+            guid: nic.guid().to_string(),
+            name: nic.name().to_string(),
+            index: nic.index().unwrap_or_default(),
+            mac: hardware
+                .get(nic.name())
+                .map(|item| item.mac.clone())
+                .filter(|mac| !mac.is_empty()),
+            hardware: hardware.get(nic.name()).cloned(),
+        })
+        .collect()
+}
+
+/// The network card of an adapter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hardware {
+    pub mac: String,
+    /// like `Intel(R) Ethernet Connection I219-V`
+    pub description: String,
+    /// as reported by the system, `None` if it's guessed from the names, see `media_type`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<MediaType>,
+    /// bits per second, `None` if unknown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_speed: Option<u64>,
+    /// the operational status, like `Up`, `Disconnected` or `Disabled`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+impl Hardware {
+    /// the media type reported, or guessed from the description and the adapter `name`
+    pub fn media_type(&self, name: &str) -> MediaType {
+        self.media_type
+            .unwrap_or_else(|| MediaType::guess(name, &self.description))
+    }
+}
+
+/// query the cards of the adapters, keyed by adapter name.
+/// `Get-NetAdapter` tells the most, `getmac` is used if it fails
+pub fn get_adapter_hardware() -> Result<HashMap<String, Hardware>> {
+    get_net_adapters().or_else(|_| get_macs())
+}
+
+/// a row of `Get-NetAdapter`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct NetAdapter {
+    name: String,
+    interface_description: Option<String>,
+    mac_address: Option<String>,
+    status: Option<String>,
+    receive_link_speed: Option<u64>,
+    #[serde(rename = "NdisPhysicalMedium")]
+    medium: Option<u32>,
+    #[serde(rename = "Virtual")]
+    is_virtual: Option<bool>,
+    hardware_interface: Option<bool>,
+}
+
+fn get_net_adapters() -> Result<HashMap<String, Hardware>> {
+    let script = "[Console]::OutputEncoding = [Text.Encoding]::UTF8; \
+        ConvertTo-Json -Compress -InputObject @(Get-NetAdapter | Select-Object Name, \
+        InterfaceDescription, MacAddress, Status, ReceiveLinkSpeed, NdisPhysicalMedium, \
+        Virtual, HardwareInterface)";
+    let mut command = Command::new("powershell.exe");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", script]);
+    let output = hidden(&mut command).output().map_err(Error::Spawn)?;
+    let failed = |output: String| Error::Command {
+        command: "powershell.exe Get-NetAdapter".to_string(),
+        output,
+    };
+    if !output.status.success() {
+        return Err(failed(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+
+    parse_net_adapters(&output.stdout).map_err(|err| failed(err.to_string()))
+}
+
+/// the cards in the JSON rows of `Get-NetAdapter`
+fn parse_net_adapters(json: &[u8]) -> serde_json::Result<HashMap<String, Hardware>> {
+    let rows: Vec<NetAdapter> = serde_json::from_slice(json)?;
+    let hardware = rows
+        .into_iter()
+        .map(|row| {
+            let media_type = row.is_virtual.zip(row.hardware_interface).and_then(
+                |(is_virtual, hardware_interface)| {
+                    MediaType::reported(is_virtual, hardware_interface, row.medium)
+                },
+            );
+            let hardware = Hardware {
+                mac: row
+                    .mac_address
+                    .as_deref()
+                    .map(normalize_mac)
+                    .unwrap_or_default(),
+                description: row.interface_description.unwrap_or_default(),
+                media_type,
+                link_speed: row.receive_link_speed.filter(|speed| *speed > 0),
+                status: row.status,
+            };
+            (row.name, hardware)
+        })
+        .collect();
+    Ok(hardware)
+}
+
+/// query the MAC addresses and the descriptions with `getmac`
+fn get_macs() -> Result<HashMap<String, Hardware>> {
+    let mut command = Command::new("getmac.exe");
+    command.args(["/v", "/fo", "csv", "/nh"]);
+    let output = hidden(&mut command).output().map_err(Error::Spawn)?;
+    let text = GB18030
+        .decode(&output.stdout, DecoderTrap::Replace)
+        .map_err(|err| Error::Encoding(err.to_string()))?;
+
+    // "Connection Name","Network Adapter","Physical Address","Transport Name"
+    let hardware = text
+        .lines()
+        .filter_map(|line| {
+            let fields = line
+                .trim()
+                .trim_matches('"')
+                .split("\",\"")
+                .collect::<Vec<_>>();
+            match fields[..] {
+                [name, description, mac, ..] if mac.contains('-') => Some((
+                    name.to_string(),
+                    Hardware {
+                        mac: normalize_mac(mac),
+                        description: description.to_string(),
+                        media_type: None,
+                        link_speed: None,
+                        status: None,
+                    },
+                )),
+                _ => None,
+            }
+        })
+        .collect();
+
+    Ok(hardware)
+}
+
+/// run `command` without a console window
+fn hidden(command: &mut Command) -> &mut Command {
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::nic;
+
+    fn adapter(guid: &str, name: &str, mac: Option<&str>) -> AdapterIdentity {
+        AdapterIdentity {
+            guid: guid.to_string(),
+            name: name.to_string(),
+            index: 1,
+            mac: mac.map(str::to_string),
+            hardware: None,
+        }
+    }
+
+    #[test]
+    fn wildcard_pattern() {
+        assert!(match_pattern("Ethernet", "ethernet"));
+        assert!(match_pattern("Ethernet*", "Ethernet 3"));
+        assert!(match_pattern("Ethernet ?", "Ethernet 3"));
+        assert!(!match_pattern("Ethernet ?", "Ethernet 10"));
+        assert!(match_pattern("*", ""));
+        assert!(match_pattern("**net*", "vEthernet (WSL)"));
+        // the first `*` has to give back what it took
+        assert!(match_pattern("*a*b", "aaab"));
+        assert!(match_pattern("*net (*)", "vEthernet (Default Switch)"));
+        assert!(!match_pattern("*net (*)", "vEthernet"));
+        assert!(!match_pattern("Wi-Fi", "Wi-Fi 2"));
+        assert!(!match_pattern("Wi-Fi?", "Wi-Fi"));
+    }
+
+    #[test]
+    fn normalized_mac() {
+        assert_eq!(normalize_mac(" 00:1a:2b:3c:4d:5e "), "00-1A-2B-3C-4D-5E");
+        assert_eq!(normalize_mac("00-1A-2B-3C-4D-5E"), "00-1A-2B-3C-4D-5E");
+        let binding = AdapterBinding::new("Ethernet", Some("00:1a:2b:3c:4d:5e".to_string()));
+        assert_eq!(binding.mac.as_deref(), Some("00-1A-2B-3C-4D-5E"));
+    }
+
+    #[test]
+    fn resolve_by_guid_then_mac_then_name() {
+        let adapters = [
+            adapter("{usb}", "Ethernet", Some("00-00-00-00-00-01")),
+            adapter("{dock}", "Ethernet 2", Some("00-00-00-00-00-02")),
+            adapter("{wifi}", "Wi-Fi", None),
+        ];
+        let binding = AdapterBinding::new("Ethernet", Some("00-00-00-00-00-02".to_string()));
+
+        let (item, by) = binding.resolve("{wifi}", &adapters).unwrap();
+        assert_eq!((item.guid.as_str(), by), ("{wifi}", MatchedBy::Guid));
+
+        let (item, by) = binding.resolve("{gone}", &adapters).unwrap();
+        assert_eq!((item.guid.as_str(), by), ("{dock}", MatchedBy::Mac));
+
+        let binding = AdapterBinding::new("ethernet*", Some("00-00-00-00-00-09".to_string()));
+        let (item, by) = binding.resolve("{gone}", &adapters).unwrap();
+        assert_eq!((item.guid.as_str(), by), ("{usb}", MatchedBy::Name));
+
+        let binding = AdapterBinding::new("USB*", None);
+        assert_eq!(binding.resolve("{gone}", &adapters), None);
+    }
+
+    #[test]
+    fn parse_get_net_adapter() {
+        let json = br#"[
+            {"Name":"Ethernet","InterfaceDescription":"Intel(R) Ethernet Connection I219-V",
+             "MacAddress":"00-1A-2B-3C-4D-5E","Status":"Up","ReceiveLinkSpeed":1000000000,
+             "NdisPhysicalMedium":14,"Virtual":false,"HardwareInterface":true},
+            {"Name":"Wi-Fi","InterfaceDescription":"Intel(R) Wi-Fi 6 AX201 160MHz",
+             "MacAddress":"a0:b1:c2:d3:e4:f5","Status":"Disconnected","ReceiveLinkSpeed":0,
+             "NdisPhysicalMedium":9,"Virtual":false,"HardwareInterface":true},
+            {"Name":"vEthernet (WSL)","InterfaceDescription":"Hyper-V Virtual Ethernet Adapter",
+             "MacAddress":"00-15-5D-00-00-01","Status":"Up","ReceiveLinkSpeed":10000000000,
+             "NdisPhysicalMedium":14,"Virtual":true,"HardwareInterface":false},
+            {"Name":"USB","InterfaceDescription":"ASIX AX88179 USB 3.0 to Gigabit Ethernet Adapter",
+             "MacAddress":null,"Status":"Disabled","ReceiveLinkSpeed":null}
+        ]"#;
+        let hardware = parse_net_adapters(json).unwrap();
+        assert_eq!(hardware.len(), 4);
+        assert_eq!(
+            hardware["Ethernet"],
+            Hardware {
+                mac: "00-1A-2B-3C-4D-5E".to_string(),
+                description: "Intel(R) Ethernet Connection I219-V".to_string(),
+                media_type: Some(MediaType::Wired),
+                link_speed: Some(1_000_000_000),
+                status: Some("Up".to_string()),
+            }
+        );
+        let wifi = &hardware["Wi-Fi"];
+        assert_eq!(wifi.mac, "A0-B1-C2-D3-E4-F5");
+        assert_eq!(wifi.media_type, Some(MediaType::Wireless));
+        assert_eq!(wifi.link_speed, None);
+        assert_eq!(
+            hardware["vEthernet (WSL)"].media_type,
+            Some(MediaType::Virtual)
+        );
+        // without the properties the type is guessed from the names
+        let usb = &hardware["USB"];
+        assert_eq!((usb.mac.as_str(), usb.media_type), ("", None));
+        assert_eq!(usb.media_type("USB"), MediaType::Wired);
+
+        assert!(parse_net_adapters(b"{\"Name\":\"Ethernet\"}").is_err());
+    }
+
+    #[test]
+    fn identify_with_the_cards() {
+        let hardware = parse_net_adapters(
+            br#"[{"Name":"Ethernet","MacAddress":"00-1A-2B-3C-4D-5E"},{"Name":"USB"}]"#,
+        )
+        .unwrap();
+        let adapters = [
+            nic("Ethernet", "{eth}").index(3).dhcp().build(),
+            nic("USB", "{usb}").index(3).dhcp().build(),
+        ];
+        let identities = identify(&adapters, &hardware);
+        assert_eq!(identities[0].mac.as_deref(), Some("00-1A-2B-3C-4D-5E"));
+        assert_eq!(identities[0].index, 3);
+        // an empty MAC is no MAC
+        assert_eq!(identities[1].mac, None);
+        assert!(identities[1].hardware.is_some());
+    }
+}
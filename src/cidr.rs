@@ -0,0 +1,152 @@
+//! Conversions between netmasks and prefix lengths, and the `192.168.3.55/24` notation.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// the netmask of `prefix` bits, for an IPv4 address if `v4`, otherwise IPv6.
+/// `None` if the prefix is too long
+pub fn prefix_to_netmask(prefix: u8, v4: bool) -> Option<IpAddr> {
+    if v4 {
+        let bits = u32::MAX
+            .checked_shl(32 - prefix.min(32) as u32)
+            .unwrap_or(0);
+        (prefix <= 32).then(|| IpAddr::V4(Ipv4Addr::from(bits)))
+    } else {
+        let bits = u128::MAX
+            .checked_shl(128 - prefix.min(128) as u32)
+            .unwrap_or(0);
+        (prefix <= 128).then(|| IpAddr::V6(Ipv6Addr::from(bits)))
+    }
+}
+
+/// the prefix length of `netmask`, `None` if the ones are not contiguous
+pub fn netmask_to_prefix(netmask: &IpAddr) -> Option<u8> {
+    let (ones, zeros, width) = match netmask {
+        IpAddr::V4(mask) => {
+            let bits = u32::from(*mask);
+            (bits.leading_ones(), bits.trailing_zeros(), 32)
+        }
+        IpAddr::V6(mask) => {
+            let bits = u128::from(*mask);
+            (bits.leading_ones(), bits.trailing_zeros(), 128)
+        }
+    };
+    // all zeros has `trailing_zeros` of the width
+    (ones + zeros.min(width) == width).then_some(ones as u8)
+}
+
+/// parse `192.168.3.55/24`, `None` if it's not an address with a valid prefix
+pub fn parse_cidr(text: &str) -> Option<(IpAddr, u8)> {
+    let (ip, prefix) = text.trim().split_once('/')?;
+    let ip = ip.trim().parse::<IpAddr>().ok()?;
+    let prefix = prefix.trim().parse::<u8>().ok()?;
+    prefix_to_netmask(prefix, ip.is_ipv4())?;
+    Some((ip, prefix))
+}
+
+/// `ip/prefix`, `None` if the netmask is not contiguous, it has no prefix to write
+pub fn format_cidr(ip: &IpAddr, netmask: &IpAddr) -> Option<String> {
+    netmask_to_prefix(netmask).map(|prefix| format!("{}/{}", ip, prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn prefix_to_netmask_v4() {
+        assert_eq!(prefix_to_netmask(24, true), Some(ip("255.255.255.0")));
+        assert_eq!(prefix_to_netmask(20, true), Some(ip("255.255.240.0")));
+        assert_eq!(prefix_to_netmask(32, true), Some(ip("255.255.255.255")));
+        assert_eq!(prefix_to_netmask(1, true), Some(ip("128.0.0.0")));
+        assert_eq!(prefix_to_netmask(0, true), Some(ip("0.0.0.0")));
+        assert_eq!(prefix_to_netmask(33, true), None);
+    }
+
+    #[test]
+    fn prefix_to_netmask_v6() {
+        assert_eq!(
+            prefix_to_netmask(64, false),
+            Some(ip("ffff:ffff:ffff:ffff::"))
+        );
+        assert_eq!(
+            prefix_to_netmask(128, false),
+            Some(ip("ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"))
+        );
+        assert_eq!(prefix_to_netmask(0, false), Some(ip("::")));
+        assert_eq!(prefix_to_netmask(129, false), None);
+    }
+
+    #[test]
+    fn netmask_to_prefix_contiguous() {
+        assert_eq!(netmask_to_prefix(&ip("255.255.255.0")), Some(24));
+        assert_eq!(netmask_to_prefix(&ip("255.255.254.0")), Some(23));
+        assert_eq!(netmask_to_prefix(&ip("255.255.255.255")), Some(32));
+        assert_eq!(netmask_to_prefix(&ip("0.0.0.0")), Some(0));
+        assert_eq!(netmask_to_prefix(&ip("ffff:ffff:ffff:ffff::")), Some(64));
+    }
+
+    #[test]
+    fn netmask_to_prefix_not_contiguous() {
+        assert_eq!(netmask_to_prefix(&ip("255.0.255.0")), None);
+        assert_eq!(netmask_to_prefix(&ip("0.255.255.255")), None);
+        assert_eq!(netmask_to_prefix(&ip("255.255.255.1")), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        for prefix in 0..=32 {
+            let mask = prefix_to_netmask(prefix, true).unwrap();
+            assert_eq!(netmask_to_prefix(&mask), Some(prefix));
+        }
+        for prefix in 0..=128 {
+            let mask = prefix_to_netmask(prefix, false).unwrap();
+            assert_eq!(netmask_to_prefix(&mask), Some(prefix));
+        }
+    }
+
+    #[test]
+    fn parse_cidr_notation() {
+        assert_eq!(
+            parse_cidr("192.168.3.55/24"),
+            Some((ip("192.168.3.55"), 24))
+        );
+        assert_eq!(parse_cidr(" 10.0.0.1 / 8 "), Some((ip("10.0.0.1"), 8)));
+        assert_eq!(parse_cidr("fe80::1/64"), Some((ip("fe80::1"), 64)));
+        assert_eq!(parse_cidr("192.168.3.55/33"), None);
+        assert_eq!(parse_cidr("192.168.3.55"), None);
+        assert_eq!(parse_cidr("192.168.3/24"), None);
+        assert_eq!(parse_cidr("192.168.3.55/x"), None);
+    }
+
+    #[test]
+    fn format_cidr_notation() {
+        assert_eq!(
+            format_cidr(&ip("192.168.3.55"), &ip("255.255.255.0")).as_deref(),
+            Some("192.168.3.55/24")
+        );
+        assert_eq!(format_cidr(&ip("192.168.3.55"), &ip("255.0.255.0")), None);
+    }
+
+    #[test]
+    fn format_cidr_parses_back() {
+        for (address, netmask) in [
+            ("192.168.3.55", "255.255.255.0"),
+            ("10.0.0.1", "255.0.0.0"),
+            ("172.16.5.4", "255.255.240.0"),
+            ("192.168.3.55", "255.255.255.255"),
+            ("fe80::1", "ffff:ffff:ffff:ffff::"),
+        ] {
+            let text = format_cidr(&ip(address), &ip(netmask)).unwrap();
+            let (parsed, prefix) = parse_cidr(&text).unwrap();
+            assert_eq!(parsed, ip(address));
+            assert_eq!(
+                prefix_to_netmask(prefix, parsed.is_ipv4()),
+                Some(ip(netmask))
+            );
+        }
+    }
+}
@@ -0,0 +1,815 @@
+//! The command line, for scripts that can't click through the window.
+//!
+//! `cfg-ip` without a command opens the window. The commands use the same config file,
+//! history and backend as the window, and `--dry-run` prints what would be done instead.
+
+use crate::backend::Backend;
+use crate::binding::{self, AdapterIdentity};
+use crate::config::{self, ConfigFile};
+use crate::form::ProfileForm;
+use crate::history::{self, History};
+use crate::json::{self, AdapterList, ApplyReport};
+use crate::overlap;
+use crate::store::{IpConfigList, Profile};
+use crate::transfer;
+use anyhow::{anyhow, Result};
+use itertools::Itertools;
+use net_adapters::adapter::Nic;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const USAGE: &str = "\
+usage: cfg-ip [--config <file> | --portable] [--dry-run] [--json] [<command>]
+
+Without a command the window is opened.
+
+commands:
+  adapters                          list the adapters of this machine
+  profiles list                     list the saved profiles
+  profiles show <name>              show a saved profile
+  profiles add <name> --adapter <adapter> (--dhcp | --addr <ip/prefix>... [--gw <ip>...] [--dns <ip>...])
+               [--group <group>]    save a profile, an address may also be an ip with --mask
+  profiles rm <name>                remove a saved profile
+  apply <profile> [--adapter <adapter>] [--var <name=value>...]
+                                    apply a saved profile, --var fills the variables of a template
+  dhcp <adapter>                    turn on DHCP
+  static <adapter> --addr <ip/prefix>... [--mask <netmask>...] [--gw <ip>...] [--dns <ip>...]
+                                    set static addresses
+
+--addr, --mask, --gw and --dns can be repeated or take a list like `8.8.8.8,1.1.1.1`.
+An adapter is given by its name or guid.
+--json prints the adapters, the profiles and the apply reports as JSON.";
+
+/// options taking a value, the others are switches
+const VALUE_OPTIONS: &[&str] = &[
+    "--config",
+    "--adapter",
+    "--addr",
+    "--mask",
+    "--gw",
+    "--dns",
+    "--group",
+    "--var",
+];
+const SWITCHES: &[&str] = &[
+    "--portable",
+    "--dry-run",
+    "--json",
+    "--dhcp",
+    "--help",
+    "-h",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Adapters,
+    ListProfiles,
+    ShowProfile(String),
+    /// `form.name` is the name of the profile
+    AddProfile {
+        form: ProfileForm,
+        adapter: String,
+        group: Option<String>,
+    },
+    RemoveProfile(String),
+    Apply {
+        profile: String,
+        adapter: Option<String>,
+        /// the values of the variables of a template
+        values: HashMap<String, String>,
+    },
+    Dhcp(String),
+    Static {
+        adapter: String,
+        form: ProfileForm,
+    },
+    Help,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Args {
+    /// `--config <file>`
+    pub config: Option<PathBuf>,
+    pub portable: bool,
+    pub dry_run: bool,
+    /// print JSON, see `crate::json`
+    pub json: bool,
+    /// `None` opens the window
+    pub command: Option<Command>,
+}
+
+impl Args {
+    /// parse the arguments without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args> {
+        let mut positional = Vec::new();
+        let mut options: Vec<(String, String)> = Vec::new();
+        let mut parsed = Args::default();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') {
+                positional.push(arg);
+                continue;
+            }
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg.clone(), None),
+            };
+            if SWITCHES.contains(&name.as_str()) {
+                if inline.is_some() {
+                    return Err(anyhow!("`{}` takes no value", name));
+                }
+                match name.as_str() {
+                    "--portable" => parsed.portable = true,
+                    "--dry-run" => parsed.dry_run = true,
+                    "--json" => parsed.json = true,
+                    "--help" | "-h" => parsed.command = Some(Command::Help),
+                    _ => options.push((name, String::new())),
+                }
+            } else if VALUE_OPTIONS.contains(&name.as_str()) {
+                let value = inline
+                    .or_else(|| args.next())
+                    .ok_or_else(|| anyhow!("`{}` needs a value", name))?;
+                match name.as_str() {
+                    "--config" => parsed.config = Some(PathBuf::from(value)),
+                    _ => options.push((name, value)),
+                }
+            } else {
+                return Err(anyhow!("unknown option `{}`", arg));
+            }
+        }
+
+        // `--help` shows the usage whatever the rest is
+        if parsed.command.is_some() {
+            return Ok(parsed);
+        }
+        if positional.is_empty() {
+            if let Some((name, _)) = options.first() {
+                return Err(anyhow!("`{}` needs a command", name));
+            }
+            return Ok(parsed);
+        }
+        parsed.command = Some(parse_command(&positional, &options)?);
+        Ok(parsed)
+    }
+
+    /// the config file given by `--config`, `CFG_IP_CONFIG` or `--portable`
+    pub fn config_path(&self) -> PathBuf {
+        let explicit = self
+            .config
+            .clone()
+            .or_else(|| std::env::var_os(config::CONFIG_ENV).map(PathBuf::from));
+        config::resolve_path(explicit, self.portable)
+    }
+}
+
+fn parse_command(positional: &[String], options: &[(String, String)]) -> Result<Command> {
+    let words = positional.iter().map(String::as_str).collect_vec();
+    let (command, allowed): (Command, &[&str]) = match words[..] {
+        ["adapters"] => (Command::Adapters, &[]),
+        ["profiles"] | ["profiles", "list"] => (Command::ListProfiles, &[]),
+        ["profiles", "show", name] => (Command::ShowProfile(name.to_string()), &[]),
+        ["profiles", "add", name] => {
+            let mut form = static_form(options);
+            form.name = name.to_string();
+            form.dhcp_on = has(options, "--dhcp");
+            if form.dhcp_on && !form.ip.is_empty() {
+                return Err(anyhow!("give either `--dhcp` or `--addr`, not both"));
+            }
+            if !form.dhcp_on && form.ip.is_empty() {
+                return Err(anyhow!("give `--dhcp` or an address by `--addr`"));
+            }
+            let adapter =
+                last(options, "--adapter").ok_or_else(|| anyhow!("`--adapter` is needed"))?;
+            let command = Command::AddProfile {
+                form,
+                adapter,
+                group: last(options, "--group"),
+            };
+            (
+                command,
+                &[
+                    "--adapter",
+                    "--dhcp",
+                    "--addr",
+                    "--mask",
+                    "--gw",
+                    "--dns",
+                    "--group",
+                ],
+            )
+        }
+        ["profiles", "rm", name] => (Command::RemoveProfile(name.to_string()), &[]),
+        ["apply", profile] => {
+            let values = values(options, "--var")
+                .map(|item| {
+                    item.split_once('=')
+                        .map(|(name, value)| (name.trim().to_string(), value.to_string()))
+                        .ok_or_else(|| anyhow!("`--var {}` is not like `name=value`", item))
+                })
+                .collect::<Result<_>>()?;
+            let command = Command::Apply {
+                profile: profile.to_string(),
+                adapter: last(options, "--adapter"),
+                values,
+            };
+            (command, &["--adapter", "--var"])
+        }
+        ["dhcp", adapter] => (Command::Dhcp(adapter.to_string()), &[]),
+        ["static", adapter] => {
+            let form = static_form(options);
+            if form.ip.is_empty() {
+                return Err(anyhow!("give an address by `--addr`"));
+            }
+            let command = Command::Static {
+                adapter: adapter.to_string(),
+                form,
+            };
+            (command, &["--addr", "--mask", "--gw", "--dns"])
+        }
+        ["help"] => (Command::Help, &[]),
+        _ => return Err(anyhow!("unknown command `{}`", positional.join(" "))),
+    };
+    if let Some((name, _)) = options
+        .iter()
+        .find(|(name, _)| !allowed.contains(&name.as_str()))
+    {
+        return Err(anyhow!(
+            "`{}` is not an option of `{}`",
+            name,
+            positional[..positional.len().min(2)].join(" ")
+        ));
+    }
+    Ok(command)
+}
+
+/// the values of a repeated option
+fn values<'a>(options: &'a [(String, String)], name: &'a str) -> impl Iterator<Item = &'a str> {
+    options
+        .iter()
+        .filter(move |(item, _)| item == name)
+        .map(|(_, value)| value.trim())
+        .filter(|value| !value.is_empty())
+}
+
+fn last(options: &[(String, String)], name: &str) -> Option<String> {
+    values(options, name).last().map(str::to_string)
+}
+
+fn has(options: &[(String, String)], name: &str) -> bool {
+    options.iter().any(|(item, _)| item == name)
+}
+
+/// the addresses of `--addr`, `--mask`, `--gw` and `--dns` as the texts of a form
+fn static_form(options: &[(String, String)]) -> ProfileForm {
+    // a value may be a list like `8.8.8.8,1.1.1.1`
+    let lines = |name| {
+        values(options, name)
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .join("\n")
+    };
+    ProfileForm {
+        ip: lines("--addr"),
+        netmask: lines("--mask"),
+        gateway: lines("--gw"),
+        dns: lines("--dns"),
+        ..ProfileForm::default()
+    }
+}
+
+/// run `args.command` with the config file `config_path`.
+/// The output is written to `out`, and the warnings to `err`
+pub fn run(
+    args: &Args,
+    config_path: &Path,
+    backend: &dyn Backend,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<()> {
+    let Some(command) = &args.command else {
+        return Ok(());
+    };
+    match command {
+        Command::Help => writeln!(out, "{}", USAGE)?,
+        Command::Adapters if args.json => {
+            let list = AdapterList::new(&backend.adapters(), &backend.hardware()());
+            writeln!(out, "{}", json::to_string(&list)?)?;
+        }
+        Command::Adapters => {
+            for nic in backend.adapters() {
+                writeln!(
+                    out,
+                    "{}\t{}\t{}",
+                    nic.name(),
+                    history::summary(&nic),
+                    nic.guid()
+                )?;
+            }
+        }
+        Command::ListProfiles => {
+            let cfg = load_config(config_path, err)?;
+            if args.json {
+                writeln!(out, "{}", transfer::profiles_to_json(cfg.items.profiles())?)?;
+                return Ok(());
+            }
+            for profile in cfg.items.profiles() {
+                writeln!(
+                    out,
+                    "{}\t{}\t{}\t{}",
+                    profile.name(),
+                    profile.binding().name_pattern,
+                    profile.group().unwrap_or("-"),
+                    profile_summary(profile)
+                )?;
+            }
+        }
+        Command::ShowProfile(name) => {
+            let cfg = load_config(config_path, err)?;
+            let profile = find_profile(&cfg, name)?;
+            match args.json {
+                true => writeln!(out, "{}", transfer::profiles_to_json([profile])?)?,
+                false => show_profile(profile, out)?,
+            }
+        }
+        Command::AddProfile {
+            form,
+            adapter,
+            group,
+        } => {
+            let adapters = binding::identify(&backend.adapters(), &backend.hardware()());
+            let adapter = find_adapter(&adapters, adapter)?;
+            let mut profile = form.to_profile(adapter)?;
+            profile.set_group(group.as_deref());
+            let mut cfg = load_config(config_path, err)?;
+            if args.dry_run {
+                // saved into a copy, so it's refused like the real one
+                let mut items = cfg.items.clone();
+                let name = save_profile(&mut items, profile)?;
+                writeln!(out, "would save `{}`", name)?;
+                if let Some(profile) = items.find_by_name(&name) {
+                    show_profile(profile, out)?;
+                }
+                return Ok(());
+            }
+            let name = cfg.modify(|cfg| save_profile(&mut cfg.items, profile))??;
+            writeln!(out, "saved `{}`", name)?;
+        }
+        Command::RemoveProfile(name) => {
+            let mut cfg = load_config(config_path, err)?;
+            find_profile(&cfg, name)?;
+            if args.dry_run {
+                writeln!(out, "would remove `{}`", name)?;
+                return Ok(());
+            }
+            cfg.modify(|cfg| {
+                let index = cfg.items.position(name)?;
+                cfg.items.remove_at(index as i32)
+            })?;
+            writeln!(out, "removed `{}`", name)?;
+        }
+        Command::Apply {
+            profile,
+            adapter,
+            values,
+        } => {
+            let cfg = load_config(config_path, err)?;
+            let profile = find_profile(&cfg, profile)?;
+            let current = backend.adapters();
+            let adapters = binding::identify(&current, &backend.hardware()());
+            let adapter = match adapter {
+                Some(adapter) => find_adapter(&adapters, adapter)?,
+                None => profile
+                    .binding()
+                    .resolve(profile.nic().guid(), &adapters)
+                    .map(|(item, _)| item)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "no adapter matches the profile `{}`, give one by `--adapter`",
+                            profile.name()
+                        )
+                    })?,
+            };
+            let nic = match profile.template() {
+                Some(_) => ProfileForm::from_profile(profile, false)
+                    .expand(values)?
+                    .to_nic(adapter)?,
+                None if !values.is_empty() => {
+                    return Err(anyhow!(
+                        "the profile `{}` is not a template, `--var` has nothing to fill",
+                        profile.name()
+                    ))
+                }
+                None => profile.nic_for(adapter)?,
+            };
+            apply(
+                args,
+                config_path,
+                backend,
+                &nic,
+                Some(profile.name()),
+                out,
+                err,
+            )?;
+        }
+        Command::Dhcp(adapter) => {
+            let adapters = binding::identify(&backend.adapters(), &backend.hardware()());
+            let form = ProfileForm {
+                dhcp_on: true,
+                ..ProfileForm::default()
+            };
+            let nic = form.to_nic(find_adapter(&adapters, adapter)?)?;
+            apply(args, config_path, backend, &nic, None, out, err)?;
+        }
+        Command::Static { adapter, form } => {
+            let adapters = binding::identify(&backend.adapters(), &backend.hardware()());
+            let nic = form.to_nic(find_adapter(&adapters, adapter)?)?;
+            apply(args, config_path, backend, &nic, None, out, err)?;
+        }
+    }
+    Ok(())
+}
+
+/// apply `nic` and record it in the history, like `Apply` in the window.
+/// The conflicts with the other adapters are warnings, scripts apply them anyway
+fn apply(
+    args: &Args,
+    config_path: &Path,
+    backend: &dyn Backend,
+    nic: &Nic,
+    profile: Option<&str>,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<()> {
+    let current = backend.adapters();
+    let mut report = ApplyReport::new(args.dry_run);
+    report.warnings = overlap::find(std::slice::from_ref(nic), &current)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let result = match args.dry_run {
+        true => Ok(String::new()),
+        false => {
+            let history_path = History::path_for(config_path);
+            let mut history = History::load(&history_path).unwrap_or_else(|err| {
+                report.warnings.push(err.to_string());
+                History::empty(&history_path)
+            });
+            history.apply(backend, nic, profile, &current)
+        }
+    };
+    report.push(nic, profile, result.as_ref().err().map(ToString::to_string));
+
+    if args.json {
+        writeln!(out, "{}", json::to_string(&report)?)?;
+    } else {
+        for warning in &report.warnings {
+            writeln!(err, "warning: {}", warning)?;
+        }
+        if result.is_ok() {
+            let done = match args.dry_run {
+                true => "would apply",
+                false => "applied",
+            };
+            writeln!(
+                out,
+                "{} to `{}`: {}",
+                done,
+                nic.name(),
+                history::summary(nic)
+            )?;
+        }
+    }
+    result?;
+    Ok(())
+}
+
+/// the config file, an empty one if it doesn't exist
+fn load_config(path: &Path, err: &mut dyn Write) -> Result<ConfigFile> {
+    // the errors of the config file tell the path
+    let (cfg, recovery) = ConfigFile::load(path)?;
+    if let Some(recovery) = recovery {
+        writeln!(
+            err,
+            "warning: the file {} is broken: {}, it's kept as {}",
+            path.display(),
+            recovery.error,
+            recovery.backup.display()
+        )?;
+    }
+    Ok(cfg)
+}
+
+/// add `profile` to `items`, under a unique name which is returned
+fn save_profile(items: &mut IpConfigList, profile: Profile) -> Result<String> {
+    let name = items.unique_name(profile.name());
+    match items.insert(profile) {
+        true => Ok(name),
+        false => Err(anyhow!("a profile with the same settings is already saved")),
+    }
+}
+
+fn find_profile<'a>(cfg: &'a ConfigFile, name: &str) -> Result<&'a Profile> {
+    cfg.items.find_by_name(name).ok_or_else(|| {
+        anyhow!(
+            "the profile `{}` is not saved, see `cfg-ip profiles list`",
+            name
+        )
+    })
+}
+
+/// the adapter named `name`, case insensitive, or with the guid `name`
+fn find_adapter<'a>(adapters: &'a [AdapterIdentity], name: &str) -> Result<&'a AdapterIdentity> {
+    adapters
+        .iter()
+        .find(|item| item.name == name || item.guid == name)
+        .or_else(|| {
+            adapters
+                .iter()
+                .find(|item| item.name.eq_ignore_ascii_case(name))
+        })
+        .ok_or_else(|| anyhow!("no adapter named `{}`, see `cfg-ip adapters`", name))
+}
+
+/// the summary of the settings, or the texts of a template
+fn profile_summary(profile: &Profile) -> String {
+    match profile.template() {
+        Some(template) => format!(
+            "template {}",
+            template
+                .texts()
+                .iter()
+                .filter(|text| !text.is_empty())
+                .map(|text| text.replace('\n', ","))
+                .join(" ")
+        ),
+        None => history::summary(profile.nic()),
+    }
+}
+
+fn show_profile(profile: &Profile, out: &mut dyn Write) -> Result<()> {
+    let form = ProfileForm::from_profile(profile, true);
+    let binding = profile.binding();
+    writeln!(out, "name: {}", profile.name())?;
+    match &binding.mac {
+        Some(mac) => writeln!(out, "adapter: {} ({})", binding.name_pattern, mac)?,
+        None => writeln!(out, "adapter: {}", binding.name_pattern)?,
+    }
+    writeln!(out, "group: {}", profile.group().unwrap_or("-"))?;
+    if form.dhcp_on {
+        writeln!(out, "dhcp: on")?;
+        return Ok(());
+    }
+    if profile.template().is_some() {
+        writeln!(out, "template: yes")?;
+    }
+    for (label, text) in [
+        ("address", &form.ip),
+        ("netmask", &form.netmask),
+        ("gateway", &form.gateway),
+        ("dns", &form.dns),
+    ] {
+        if !text.is_empty() {
+            writeln!(out, "{}: {}", label, text.replace('\n', ", "))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{nic, FakeBackend, TempDir};
+
+    fn parse(args: &[&str]) -> Result<Args> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_command_opens_the_window() {
+        let args = parse(&["--portable"]).unwrap();
+        assert!(args.portable);
+        assert_eq!(args.command, None);
+        let args = parse(&["--config=lab.yml"]).unwrap();
+        assert_eq!(args.config, Some(PathBuf::from("lab.yml")));
+        assert_eq!(args.command, None);
+    }
+
+    #[test]
+    fn static_command() {
+        let args = parse(&[
+            "static",
+            "Ethernet",
+            "--addr",
+            "10.0.0.5/24",
+            "--gw=10.0.0.1",
+            "--dns",
+            "8.8.8.8,1.1.1.1",
+            "--dry-run",
+        ])
+        .unwrap();
+        assert!(args.dry_run);
+        let Some(Command::Static { adapter, form }) = args.command else {
+            panic!("not a static command");
+        };
+        assert_eq!(adapter, "Ethernet");
+        assert_eq!(form.ip, "10.0.0.5/24");
+        assert_eq!(form.gateway, "10.0.0.1");
+        assert_eq!(form.dns, "8.8.8.8\n1.1.1.1");
+    }
+
+    #[test]
+    fn apply_with_variables() {
+        let args = parse(&["apply", "Site", "--var", "site=20", "--adapter", "USB"]).unwrap();
+        let Some(Command::Apply {
+            profile,
+            adapter,
+            values,
+        }) = args.command
+        else {
+            panic!("not an apply command");
+        };
+        assert_eq!(profile, "Site");
+        assert_eq!(adapter.as_deref(), Some("USB"));
+        assert_eq!(values.get("site").map(String::as_str), Some("20"));
+    }
+
+    #[test]
+    fn wrong_arguments() {
+        assert!(parse(&["static", "Ethernet"]).is_err());
+        assert!(parse(&["dhcp", "Ethernet", "--gw", "10.0.0.1"]).is_err());
+        assert!(parse(&["profiles", "add", "Lab", "--dhcp"]).is_err());
+        assert!(parse(&["profiles", "add", "Lab", "--adapter", "USB"]).is_err());
+        assert!(parse(&["apply", "Lab", "--var", "site"]).is_err());
+        assert!(parse(&["dhcp", "--adapter"]).is_err());
+        assert!(parse(&["reboot"]).is_err());
+        assert!(parse(&["--gw", "10.0.0.1"]).is_err());
+    }
+
+    /// a config file in a directory of its own, and the adapters `Ethernet` and `Wi-Fi` on DHCP
+    struct Fixture {
+        dir: TempDir,
+        backend: FakeBackend,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            Fixture {
+                dir: TempDir::new(&format!("cli-{}", name)),
+                backend: FakeBackend::new(vec![
+                    nic("Ethernet", "{eth}").dhcp().build(),
+                    nic("Wi-Fi", "{wifi}").dhcp().build(),
+                ]),
+            }
+        }
+
+        /// run the command line `args`, and return the output
+        fn run(&self, args: &[&str]) -> Result<String> {
+            let args = parse(args)?;
+            let mut out = Vec::new();
+            run(
+                &args,
+                &self.config(),
+                &self.backend,
+                &mut out,
+                &mut std::io::sink(),
+            )?;
+            Ok(String::from_utf8(out).unwrap())
+        }
+
+        fn config(&self) -> PathBuf {
+            self.dir.join(config::CONFIG_FILE_NAME)
+        }
+
+        fn saved(&self) -> Vec<String> {
+            let (cfg, _) = ConfigFile::load(self.config()).unwrap();
+            cfg.items
+                .profiles()
+                .iter()
+                .map(|item| item.name().to_string())
+                .collect()
+        }
+
+        fn applied(&self) -> Vec<String> {
+            self.backend.applied_summaries()
+        }
+    }
+
+    const ADD_OFFICE: &[&str] = &[
+        "profiles",
+        "add",
+        "Office",
+        "--adapter",
+        "ethernet",
+        "--addr",
+        "192.168.3.55/24",
+        "--gw",
+        "192.168.3.1",
+    ];
+
+    #[test]
+    fn add_and_remove_profiles() {
+        let cli = Fixture::new("profiles");
+        let dry_run = [ADD_OFFICE, &["--dry-run"]].concat();
+        assert!(cli
+            .run(&dry_run)
+            .unwrap()
+            .starts_with("would save `Office`\nname: Office\n"));
+        assert!(cli.saved().is_empty());
+
+        assert_eq!(cli.run(ADD_OFFICE).unwrap(), "saved `Office`\n");
+        assert_eq!(cli.saved(), ["Office"]);
+        assert_eq!(
+            cli.run(&["profiles", "list"]).unwrap(),
+            "Office\tEthernet\t-\tstatic 192.168.3.55/255.255.255.0 gw 192.168.3.1\n"
+        );
+        assert_eq!(
+            cli.run(ADD_OFFICE).unwrap_err().to_string(),
+            "a profile with the same settings is already saved"
+        );
+        assert_eq!(
+            cli.run(&dry_run).unwrap_err().to_string(),
+            "a profile with the same settings is already saved"
+        );
+        let renamed = [&dry_run, &["--dns", "8.8.8.8"][..]].concat();
+        assert!(cli
+            .run(&renamed)
+            .unwrap()
+            .starts_with("would save `Office (2)`\nname: Office (2)\n"));
+        assert_eq!(cli.saved(), ["Office"]);
+
+        assert_eq!(
+            cli.run(&["profiles", "rm", "Office"]).unwrap(),
+            "removed `Office`\n"
+        );
+        assert!(cli.saved().is_empty());
+        assert!(cli.run(&["profiles", "rm", "Office"]).is_err());
+        assert!(cli.backend.applied.borrow().is_empty());
+    }
+
+    #[test]
+    fn dry_run_leaves_the_adapters() {
+        let cli = Fixture::new("dry-run");
+        cli.run(ADD_OFFICE).unwrap();
+        assert_eq!(
+            cli.run(&["apply", "Office", "--dry-run"]).unwrap(),
+            "would apply to `Ethernet`: static 192.168.3.55/255.255.255.0 gw 192.168.3.1\n"
+        );
+        assert!(cli.applied().is_empty());
+        assert!(!History::path_for(&cli.config()).exists());
+
+        cli.run(&["apply", "Office"]).unwrap();
+        assert_eq!(
+            cli.applied(),
+            ["Ethernet static 192.168.3.55/255.255.255.0 gw 192.168.3.1"]
+        );
+        let history = History::load(History::path_for(&cli.config())).unwrap();
+        assert_eq!(history.entries().len(), 1);
+    }
+
+    #[test]
+    fn apply_a_template_with_variables() {
+        let cli = Fixture::new("template");
+        let add = ["profiles", "add", "Site", "--adapter", "Wi-Fi", "--addr"];
+        cli.run(&[&add[..], &["10.20.{site}.50/24"]].concat())
+            .unwrap();
+        assert_eq!(
+            cli.run(&["apply", "Site"]).unwrap_err().to_string(),
+            "no value for `{site}`"
+        );
+
+        cli.run(&["apply", "Site", "--var", "site=7", "--adapter", "Ethernet"])
+            .unwrap();
+        assert_eq!(cli.applied(), ["Ethernet static 10.20.7.50/255.255.255.0"]);
+
+        // a profile without variables refuses them
+        cli.run(ADD_OFFICE).unwrap();
+        assert_eq!(
+            cli.run(&["apply", "Office", "--var", "site=7"])
+                .unwrap_err()
+                .to_string(),
+            "the profile `Office` is not a template, `--var` has nothing to fill"
+        );
+        assert_eq!(cli.applied().len(), 1);
+    }
+
+    #[test]
+    fn json_output() {
+        let cli = Fixture::new("json");
+        cli.run(ADD_OFFICE).unwrap();
+
+        let text = cli.run(&["--json", "apply", "Office"]).unwrap();
+        let report: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(report["format"], json::APPLY_FORMAT);
+        assert_eq!(report["dry_run"], false);
+        assert_eq!(report["ok"], true);
+        assert_eq!(report["steps"][0]["adapter"], "Ethernet");
+        assert_eq!(report["steps"][0]["profile"], "Office");
+
+        let text = cli.run(&["--json", "profiles", "list"]).unwrap();
+        let profiles: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(profiles["profiles"][0]["name"], "Office");
+    }
+}
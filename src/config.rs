@@ -0,0 +1,735 @@
+//! The config file of the app, with the saved profiles.
+//!
+//! The file is `saved_items.yml` in the per-user config directory, `%APPDATA%\cfg-ip` on Windows
+//! and `$XDG_CONFIG_HOME/cfg-ip` elsewhere. In portable mode it's next to the executable,
+//! and `--config <file>` or the `CFG_IP_CONFIG` environment variable points to any other file.
+//!
+//! The file is versioned by `version`. When the layout changes, increase `CONFIG_VERSION`
+//! and append a migration to `MIGRATIONS`, old files are upgraded when loading.
+//! A file that can't be loaded is never deleted, it's kept as a backup and
+//! the valid profiles in it are recovered. A file of a newer version is refused, and never
+//! written, so the fields of the newer app are kept.
+
+use crate::media::AdapterFilter;
+use crate::store::{IpConfigList, Profile};
+use crate::{Error, Result};
+use anyhow::anyhow;
+use fs4::fs_std::FileExt;
+use net_adapters::adapter::Nic;
+use serde_derive::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_VERSION: u32 = 1;
+pub const CONFIG_FILE_NAME: &str = "saved_items.yml";
+/// environment variable with the path of the config file
+pub const CONFIG_ENV: &str = "CFG_IP_CONFIG";
+/// a file next to the executable that turns on portable mode
+pub const PORTABLE_MARKER: &str = "portable";
+const APP_DIR: &str = "cfg-ip";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Config {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub items: IpConfigList,
+    /// the types of adapters shown besides the physical ones
+    #[serde(default)]
+    pub adapter_filter: AdapterFilter,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: CONFIG_VERSION,
+            items: IpConfigList::default(),
+            adapter_filter: AdapterFilter::default(),
+        }
+    }
+}
+
+/// A `Config` and the file it's stored in.
+///
+/// Several instances of the app may share the file. Changes are made by `modify`, under an
+/// advisory lock of `<file>.lock`, and the file is replaced atomically. If another instance
+/// has changed the file, it's reloaded and the change is applied on top of it
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    path: PathBuf,
+    config: Config,
+    /// the content of the file when it was last read or written by us
+    disk_text: Option<String>,
+    /// the content of the file that failed to reload, so it's reported only once
+    rejected_text: Option<String>,
+}
+
+impl ConfigFile {
+    /// see `load`
+    pub fn load(path: impl Into<PathBuf>) -> Result<(Self, Option<Recovery>)> {
+        let path = path.into();
+        let _lock = lock(&path)?;
+        let (config, recovery, disk_text) = load(&path)?;
+        Ok((
+            ConfigFile {
+                path,
+                config,
+                disk_text,
+                rejected_text: None,
+            },
+            recovery,
+        ))
+    }
+
+    /// an empty config for `path`, the file is untouched until `modify`
+    pub fn empty(path: impl Into<PathBuf>) -> Self {
+        ConfigFile {
+            path: path.into(),
+            config: Config::default(),
+            disk_text: None,
+            rejected_text: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// reload the file if it's changed since it was last read or written, by hand or by
+    /// another instance. Returns whether the config is changed.
+    /// If the file can't be parsed, the config in memory is kept and the error is returned,
+    /// the same content is not reported again. The file is left as is, `modify` keeps it as backup
+    pub fn reload(&mut self) -> Result<bool> {
+        let _lock = lock(&self.path)?;
+
+        let disk_text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => Some(text),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(Error::store(&self.path)(err)),
+        };
+        let Some(text) = disk_text.filter(|text| Some(text) != self.disk_text.as_ref()) else {
+            // unchanged, or removed, a removed file is written again by `modify`
+            return Ok(false);
+        };
+        if self.rejected_text.as_ref() == Some(&text) {
+            return Ok(false);
+        }
+
+        match parse(&text) {
+            Ok(config) => {
+                self.config = config;
+                self.disk_text = Some(text);
+                self.rejected_text = None;
+                Ok(true)
+            }
+            Err(err) => {
+                self.rejected_text = Some(text);
+                Err(Error::format(&self.path)(err))
+            }
+        }
+    }
+
+    /// change the config by `f` and write it to the file.
+    /// `f` is applied to the latest content of the file, so it should find items by name
+    /// rather than by index, the index may change if another instance has changed the file
+    pub fn modify<R>(&mut self, f: impl FnOnce(&mut Config) -> R) -> Result<R> {
+        let _lock = lock(&self.path)?;
+
+        let disk_text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => Some(text),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(Error::store(&self.path)(err)),
+        };
+        if disk_text.is_some() && disk_text != self.disk_text {
+            // changed by another instance, a broken file is kept as backup by `load`
+            let (config, _, disk_text) = load(&self.path)?;
+            self.config = config;
+            self.disk_text = disk_text;
+        }
+
+        let result = f(&mut self.config);
+        self.disk_text = Some(store(&self.path, &self.config)?);
+
+        Ok(result)
+    }
+}
+
+impl Deref for ConfigFile {
+    type Target = Config;
+
+    fn deref(&self) -> &Self::Target {
+        &self.config
+    }
+}
+
+impl DerefMut for ConfigFile {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.config
+    }
+}
+
+/// where the config file is: `explicit` if given, next to the executable in portable mode,
+/// otherwise in the per-user config directory.
+/// Portable mode is on by `portable`, or when the executable has a `portable` file
+/// or a config file next to it
+pub fn resolve_path(explicit: Option<PathBuf>, portable: bool) -> PathBuf {
+    if let Some(path) = explicit {
+        return path;
+    }
+
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    if let Some(dir) = exe_dir {
+        if portable || dir.join(PORTABLE_MARKER).exists() || dir.join(CONFIG_FILE_NAME).exists() {
+            return dir.join(CONFIG_FILE_NAME);
+        }
+    }
+
+    match user_config_dir() {
+        Some(dir) => dir.join(APP_DIR).join(CONFIG_FILE_NAME),
+        None => PathBuf::from(CONFIG_FILE_NAME),
+    }
+}
+
+fn user_config_dir() -> Option<PathBuf> {
+    let from_env = |key| {
+        std::env::var_os(key)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    };
+
+    if cfg!(target_os = "windows") {
+        from_env("APPDATA")
+    } else {
+        from_env("XDG_CONFIG_HOME").or_else(|| from_env("HOME").map(|home| home.join(".config")))
+    }
+}
+
+/// What happened when a broken file is loaded
+#[derive(Debug, Clone)]
+pub struct Recovery {
+    /// where the broken file is kept
+    pub backup: PathBuf,
+    /// number of the profiles recovered from it
+    pub recovered: usize,
+    /// why the file can't be loaded
+    pub error: String,
+}
+
+/// upgrade a file of version `n` to `n + 1`
+type Migration = fn(&mut Value) -> anyhow::Result<()>;
+
+/// `MIGRATIONS[n]` upgrades version `n`, the length must be `CONFIG_VERSION`
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [migrate_keyed_by_guid];
+
+/// version 0 keeps the settings in `items.inner`, keyed by adapter guid
+fn migrate_keyed_by_guid(value: &mut Value) -> anyhow::Result<()> {
+    let Some(items) = value.get_mut("items").and_then(Value::as_mapping_mut) else {
+        return Ok(());
+    };
+    let Some(inner) = items.remove("inner") else {
+        return Ok(());
+    };
+    let inner = match inner {
+        Value::Mapping(inner) => inner,
+        _ => return Err(anyhow!("`items.inner` should be a map")),
+    };
+
+    let mut profiles = match items.remove("profiles") {
+        Some(Value::Sequence(profiles)) => profiles,
+        _ => Vec::new(),
+    };
+    for (_, nics) in inner {
+        let Value::Sequence(nics) = nics else {
+            return Err(anyhow!("`items.inner` should be a map of lists"));
+        };
+        for nic in nics {
+            let name = nic.get("name").cloned().unwrap_or_default();
+            let mut binding = Mapping::new();
+            binding.insert("name_pattern".into(), name);
+
+            let mut profile = Mapping::new();
+            profile.insert("nic".into(), nic);
+            profile.insert("binding".into(), Value::Mapping(binding));
+            profiles.push(Value::Mapping(profile));
+        }
+    }
+    items.insert("profiles".into(), Value::Sequence(profiles));
+
+    Ok(())
+}
+
+fn parse(text: &str) -> anyhow::Result<Config> {
+    let mut value: Value = serde_yaml::from_str(text)?;
+    if value.is_null() {
+        // an empty file
+        return Ok(Config::default());
+    }
+
+    let version = match value.get("version") {
+        Some(version) => version
+            .as_u64()
+            .ok_or(anyhow!("`version` should be a number"))?,
+        None => 0,
+    };
+    if version > CONFIG_VERSION as u64 {
+        return Err(anyhow!(
+            "the file is made by a newer version of the app, config version {}",
+            version
+        ));
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&mut value)?;
+    }
+
+    let mut cfg: Config = serde_yaml::from_value(value)?;
+    cfg.version = CONFIG_VERSION;
+    Ok(cfg)
+}
+
+/// the version of a file made by a newer version of the app, `None` if it's not newer
+fn newer_version(text: &str) -> Option<u64> {
+    serde_yaml::from_str::<Value>(text)
+        .ok()?
+        .get("version")?
+        .as_u64()
+        .filter(|version| *version > CONFIG_VERSION as u64)
+}
+
+/// load the config in `path`, a missing file gives the default config.
+/// A file of a newer version is refused and left as is.
+/// If the file is broken, it's renamed to a timestamped backup,
+/// and the profiles recovered from it are written to `path`.
+/// The content of the file is returned too, the caller should hold the lock
+fn load(path: &Path) -> Result<(Config, Option<Recovery>, Option<String>)> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            return Ok((Config::default(), None, None))
+        }
+        Err(err) => return Err(Error::store(path)(err)),
+    };
+
+    // a newer app may have written fields this one would drop, so the file is left alone
+    if let Some(version) = newer_version(&text) {
+        return Err(Error::Format {
+            path: path.to_path_buf(),
+            message: format!(
+                "the file is made by a newer version of the app, config version {}, it's not changed",
+                version
+            ),
+        });
+    }
+    let error = match parse(&text) {
+        Ok(cfg) => return Ok((cfg, None, Some(text))),
+        Err(err) => err.to_string(),
+    };
+
+    let backup = backup_path(path);
+    std::fs::rename(path, &backup).map_err(Error::store(path))?;
+
+    let mut cfg = Config::default();
+    for profile in recover(&text) {
+        cfg.items.insert(profile);
+    }
+    let text = store(path, &cfg)?;
+
+    let recovered = cfg.items.profiles().len();
+    Ok((
+        cfg,
+        Some(Recovery {
+            backup,
+            recovered,
+            error,
+        }),
+        Some(text),
+    ))
+}
+
+/// `path.<timestamp>.bak`, a number is appended if it's already used
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".{}", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+
+    let mut count = 1;
+    loop {
+        let mut candidate = backup.clone();
+        match count {
+            1 => candidate.push(".bak"),
+            _ => candidate.push(format!("-{}.bak", count)),
+        }
+        let candidate = PathBuf::from(candidate);
+        if !candidate.exists() {
+            return candidate;
+        }
+        count += 1;
+    }
+}
+
+/// hold an advisory lock of `<path>.lock` until the returned file is dropped
+pub(crate) fn lock(path: &Path) -> Result<File> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    create_parent_dir(path)?;
+
+    let lock_path = PathBuf::from(lock_path);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(Error::store(&lock_path))?;
+    file.lock_exclusive().map_err(Error::store(&lock_path))?;
+    Ok(file)
+}
+
+fn create_parent_dir(path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(Error::store(dir))?;
+    }
+    Ok(())
+}
+
+/// write `cfg` to `path` by `write_atomically`. Returns the written text
+fn store(path: &Path, cfg: &Config) -> Result<String> {
+    let text = serde_yaml::to_string(cfg).map_err(Error::format(path))?;
+    write_atomically(path, &text)?;
+    Ok(text)
+}
+
+/// write `text` to a temporary file and rename it to `path`, so the file is never half written
+pub(crate) fn write_atomically(path: &Path, text: &str) -> Result<()> {
+    create_parent_dir(path)?;
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = PathBuf::from(temp_path);
+
+    let written = File::create(&temp_path).and_then(|mut file| {
+        file.write_all(text.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(err) = written.and_then(|_| std::fs::rename(&temp_path, path)) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(Error::store(path)(err));
+    }
+
+    Ok(())
+}
+
+/// best effort to find the valid profiles in a broken file
+fn recover(text: &str) -> Vec<Profile> {
+    match serde_yaml::from_str::<Value>(text) {
+        Ok(value) => recover_value(&value),
+        Err(_) => recover_chunks(text),
+    }
+}
+
+fn parse_profile(value: Value) -> Option<Profile> {
+    match serde_yaml::from_value::<Profile>(value.clone()) {
+        Ok(profile) => Some(profile),
+        // a setting of the old layout
+        Err(_) => serde_yaml::from_value::<Nic>(value).ok().map(Profile::from),
+    }
+}
+
+/// the file is valid YAML, but some of the profiles are not
+fn recover_value(value: &Value) -> Vec<Profile> {
+    let items = &value["items"];
+    let profiles = items["profiles"]
+        .as_sequence()
+        .into_iter()
+        .flatten()
+        .cloned();
+    let old_items = items["inner"]
+        .as_mapping()
+        .into_iter()
+        .flat_map(|inner| inner.values())
+        .filter_map(Value::as_sequence)
+        .flatten()
+        .cloned();
+
+    profiles
+        .chain(old_items)
+        .filter_map(parse_profile)
+        .collect()
+}
+
+/// the file is not valid YAML, try each list item starting with `- name:` or `- nic:` on its own
+fn recover_chunks(text: &str) -> Vec<Profile> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let indent_of = |line: &str| line.len() - line.trim_start().len();
+
+    let mut profiles = Vec::new();
+    for (start, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if !trimmed.starts_with("- name:") && !trimmed.starts_with("- nic:") {
+            continue;
+        }
+
+        let indent = indent_of(line);
+        let end = lines[start + 1..]
+            .iter()
+            .position(|line| !line.trim().is_empty() && indent_of(line) <= indent)
+            .map_or(lines.len(), |len| start + 1 + len);
+        let chunk = lines[start..end]
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let line = line.get(indent..).unwrap_or_default();
+                match i {
+                    0 => line.replacen("- ", "  ", 1),
+                    _ => line.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if let Some(profile) = serde_yaml::from_str::<Value>(&chunk)
+            .ok()
+            .and_then(parse_profile)
+        {
+            profiles.push(profile);
+        }
+    }
+
+    profiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{nic, TempDir};
+
+    fn config(profiles: &[(&str, &str)]) -> Config {
+        let mut cfg = Config::default();
+        for (name, ip) in profiles {
+            assert!(cfg.items.insert(Profile::new(
+                name,
+                nic("Ethernet", "{eth}").ip(&format!("{}/24", ip)).build(),
+                None
+            )));
+        }
+        cfg
+    }
+
+    fn names(cfg: &Config) -> Vec<&str> {
+        cfg.items.profiles().iter().map(Profile::name).collect()
+    }
+
+    #[test]
+    fn migrate_version_0() {
+        // the settings keyed by adapter guid, without a version
+        let mut inner = Mapping::new();
+        inner.insert(
+            "{eth}".into(),
+            serde_yaml::to_value(vec![
+                nic("Ethernet", "{eth}").ip("192.168.3.55/24").build(),
+                nic("Ethernet", "{eth}").ip("10.0.0.5/24").build(),
+            ])
+            .unwrap(),
+        );
+        inner.insert(
+            "{wifi}".into(),
+            serde_yaml::to_value(vec![nic("Wi-Fi", "{wifi}").ip("172.16.0.5/24").build()]).unwrap(),
+        );
+        let mut items = Mapping::new();
+        items.insert("inner".into(), Value::Mapping(inner));
+        let mut file = Mapping::new();
+        file.insert("items".into(), Value::Mapping(items));
+        let text = serde_yaml::to_string(&file).unwrap();
+
+        let cfg = parse(&text).unwrap();
+        assert_eq!(cfg.version, CONFIG_VERSION);
+        let profiles = cfg.items.profiles();
+        assert_eq!(profiles.len(), 3);
+        let wifi = profiles
+            .iter()
+            .find(|item| item.nic().guid() == "{wifi}")
+            .unwrap();
+        assert_eq!(wifi.binding().name_pattern, "Wi-Fi");
+        assert_eq!(wifi.name(), "Wi-Fi - static");
+        assert_eq!(wifi.nic().address()[0].ip.to_string(), "172.16.0.5");
+    }
+
+    #[test]
+    fn current_version_round_trip() {
+        let cfg = config(&[("Office", "192.168.3.55"), ("Lab", "10.0.0.5")]);
+        let text = serde_yaml::to_string(&cfg).unwrap();
+        let parsed = parse(&text).unwrap();
+        assert_eq!(names(&parsed), ["Office", "Lab"]);
+        assert_eq!(parse("").unwrap().items.profiles().len(), 0);
+    }
+
+    #[test]
+    fn recover_valid_profiles_of_valid_yaml() {
+        let mut value =
+            serde_yaml::to_value(config(&[("Office", "192.168.3.55"), ("Lab", "10.0.0.5")]))
+                .unwrap();
+        value["items"]["profiles"][1]["nic"] = Value::String("broken".to_string());
+        let text = serde_yaml::to_string(&value).unwrap();
+        assert!(parse(&text).is_err());
+
+        let recovered = recover(&text);
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].name(), "Office");
+    }
+
+    #[test]
+    fn recover_chunks_of_broken_yaml() {
+        let text = serde_yaml::to_string(&config(&[
+            ("Office", "192.168.3.55"),
+            ("Lab", "10.0.0.5"),
+            ("Home", "192.168.1.5"),
+        ]))
+        .unwrap();
+        // break the middle profile, and so the whole file
+        let text = text.replacen("- name: Lab\n", "- name: Lab\n    binding: [unclosed\n", 1);
+        assert!(serde_yaml::from_str::<Value>(&text).is_err());
+
+        let recovered = recover(&text);
+        let names = recovered.iter().map(Profile::name).collect::<Vec<_>>();
+        assert_eq!(names, ["Office", "Home"]);
+    }
+
+    #[test]
+    fn broken_file_is_kept_as_backup() {
+        let dir = TempDir::new("config-broken");
+        let path = dir.join(CONFIG_FILE_NAME);
+        let text = serde_yaml::to_string(&config(&[("Office", "192.168.3.55")])).unwrap();
+        let broken = format!("{}items: [unclosed\n", text);
+        std::fs::write(&path, &broken).unwrap();
+
+        let (cfg, recovery) = ConfigFile::load(&path).unwrap();
+        let recovery = recovery.unwrap();
+        assert_eq!(recovery.recovered, 1);
+        assert_eq!(names(&cfg), ["Office"]);
+        assert_eq!(std::fs::read_to_string(&recovery.backup).unwrap(), broken);
+        let backup = recovery.backup.file_name().unwrap().to_string_lossy();
+        assert!(backup.starts_with("saved_items.yml."), "{}", backup);
+        assert!(backup.ends_with(".bak"), "{}", backup);
+        // the recovered profiles are written in its place
+        let (cfg, recovery) = ConfigFile::load(&path).unwrap();
+        assert!(recovery.is_none());
+        assert_eq!(names(&cfg), ["Office"]);
+    }
+
+    #[test]
+    fn backup_names_are_not_reused() {
+        let dir = TempDir::new("config-backup");
+        let path = dir.join(CONFIG_FILE_NAME);
+        let first = backup_path(&path);
+        std::fs::write(&first, "").unwrap();
+        let second = backup_path(&path);
+        assert_ne!(first, second);
+
+        let first = first.to_string_lossy().to_string();
+        let second = second.to_string_lossy().to_string();
+        // `saved_items.yml.<time>.bak`, then `saved_items.yml.<time>-2.bak` in the same second
+        let stamp = first.trim_end_matches(".bak");
+        if second.starts_with(stamp) {
+            assert_eq!(second, format!("{}-2.bak", stamp));
+        }
+    }
+
+    #[test]
+    fn newer_version_is_left_alone() {
+        let dir = TempDir::new("config-newer");
+        let path = dir.join(CONFIG_FILE_NAME);
+        let text = format!(
+            "version: {}\nitems:\n  profiles: []\n  future: kept\n",
+            CONFIG_VERSION + 1
+        );
+        std::fs::write(&path, &text).unwrap();
+
+        let err = ConfigFile::load(&path).unwrap_err();
+        assert!(err.to_string().contains("newer version"), "{}", err);
+        // an instance that was already running doesn't write over it either
+        let mut cfg = ConfigFile::empty(&path);
+        assert!(cfg.reload().is_err());
+        assert!(cfg.modify(|cfg| cfg.items.add_group("Site A")).is_err());
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+        assert_eq!(dir.files(), [CONFIG_FILE_NAME, "saved_items.yml.lock"]);
+    }
+
+    #[test]
+    fn store_replaces_the_file_atomically() {
+        let dir = TempDir::new("config-store");
+        let path = dir.join("sub").join(CONFIG_FILE_NAME);
+        let text = store(&path, &config(&[("Office", "192.168.3.55")])).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+        let text = store(&path, &config(&[("Lab", "10.0.0.5")])).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+        assert_eq!(names(&parse(&text).unwrap()), ["Lab"]);
+        // no temporary file is left
+        let files = std::fs::read_dir(dir.join("sub")).unwrap().count();
+        assert_eq!(files, 1);
+
+        // the rename fails on a directory, the temporary file is removed
+        let taken = dir.join("taken");
+        std::fs::create_dir_all(taken.join("inside")).unwrap();
+        assert!(store(&taken, &Config::default()).is_err());
+        assert_eq!(dir.files(), ["sub", "taken"]);
+    }
+
+    #[test]
+    fn modify_applies_on_top_of_other_instances() {
+        let dir = TempDir::new("config-modify");
+        let path = dir.join(CONFIG_FILE_NAME);
+        let mut first = ConfigFile::empty(&path);
+        let mut second = ConfigFile::load(&path).unwrap().0;
+
+        first
+            .modify(|cfg| {
+                cfg.items.insert(Profile::new(
+                    "Office",
+                    nic("Ethernet", "{eth}").ip("192.168.3.55/24").build(),
+                    None,
+                ))
+            })
+            .unwrap();
+        // `second` hasn't seen `Office`, it's read again before the change
+        second
+            .modify(|cfg| {
+                cfg.items.insert(Profile::new(
+                    "Lab",
+                    nic("Ethernet", "{eth}").ip("10.0.0.5/24").build(),
+                    None,
+                ))
+            })
+            .unwrap();
+        assert_eq!(names(&second), ["Office", "Lab"]);
+
+        assert!(first.reload().unwrap());
+        assert_eq!(names(&first), ["Office", "Lab"]);
+        assert!(!first.reload().unwrap());
+    }
+
+    #[test]
+    fn concurrent_modify_keeps_every_change() {
+        let dir = TempDir::new("config-concurrent");
+        let path = dir.join(CONFIG_FILE_NAME);
+        let threads = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let mut cfg = ConfigFile::empty(&path);
+                    for j in 0..5 {
+                        cfg.modify(|cfg| cfg.items.add_group(&format!("group {}-{}", i, j)))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let (cfg, _) = ConfigFile::load(&path).unwrap();
+        assert_eq!(cfg.items.groups().len(), 40);
+    }
+}
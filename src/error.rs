@@ -0,0 +1,87 @@
+//! The errors of checking, applying and storing the settings.
+//!
+//! The UI shows them in a dialog, a `Validation` error also points at the wrong line.
+
+use crate::validate::FieldError;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum Error {
+    /// a line of the edit pane is wrong
+    Validation(FieldError),
+    /// the variables of a template are wrong, or a value is missing
+    Template(String),
+    /// the settings are refused for the adapter, a scene can't be planned,
+    /// or an address of the subnet calculator is wrong
+    Settings(String),
+    /// a command can't be encoded for the shell, its output can't be decoded,
+    /// or a document can't be written as JSON
+    Encoding(String),
+    /// the shell can't be started, or the commands can't be sent to it
+    Spawn(io::Error),
+    /// a command of the shell failed
+    Command { command: String, output: String },
+    /// the config file, the history or an exchange file can't be read or written
+    Store { path: PathBuf, source: io::Error },
+    /// the config file, the history or an exchange file can't be parsed or serialized
+    Format { path: PathBuf, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub(crate) fn store(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Error {
+        let path = path.into();
+        move |source| Error::Store { path, source }
+    }
+
+    pub(crate) fn settings(err: impl fmt::Display) -> Error {
+        Error::Settings(err.to_string())
+    }
+
+    pub(crate) fn format<E: fmt::Display>(path: impl Into<PathBuf>) -> impl FnOnce(E) -> Error {
+        let path = path.into();
+        move |err| Error::Format {
+            path,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Validation(err) => write!(f, "{}", err),
+            Error::Template(message) | Error::Settings(message) => write!(f, "{}", message),
+            Error::Encoding(message) => write!(f, "encoding failed: {}", message),
+            Error::Spawn(err) => write!(f, "can't run the shell: {}", err),
+            Error::Command { command, output } => {
+                write!(f, "`{}` failed", command)?;
+                match output.trim() {
+                    "" => Ok(()),
+                    output => write!(f, ": {}", output),
+                }
+            }
+            Error::Store { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Format { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Validation(err) => Some(err),
+            Error::Spawn(err) | Error::Store { source: err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<FieldError> for Error {
+    fn from(err: FieldError) -> Self {
+        Error::Validation(err)
+    }
+}
@@ -0,0 +1,442 @@
+//! The edit pane as plain text, without the UI.
+//!
+//! A form is filled from a profile or an adapter, edited as text, and turned back into
+//! checked settings. The texts are `ip, netmask, gateway, dns` with an address a line,
+//! an ip may be like `192.168.3.55/24`. Texts with `{variables}` make a template.
+
+use crate::binding::AdapterIdentity;
+use crate::cidr;
+use crate::store::Profile;
+use crate::template::Template;
+use crate::validate::{self, Field};
+use crate::{Error, Result};
+use itertools::Itertools;
+use net_adapters::adapter::{Address, Nic};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileForm {
+    /// the profile name, a default name is made if it's empty
+    pub name: String,
+    pub dhcp_on: bool,
+    pub ip: String,
+    pub netmask: String,
+    pub gateway: String,
+    pub dns: String,
+}
+
+/// The checked addresses of a form
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Addresses {
+    pub address: Vec<Address>,
+    pub gateway: Vec<IpAddr>,
+    pub dns: Vec<IpAddr>,
+}
+
+impl ProfileForm {
+    /// a form of the texts `ip, netmask, gateway, dns`, a missing text is empty
+    pub fn from_texts<S: AsRef<str>>(dhcp_on: bool, texts: &[S]) -> Self {
+        let text = |field: Field| {
+            texts
+                .get(field.index())
+                .map_or_else(String::new, |text| text.as_ref().to_string())
+        };
+        ProfileForm {
+            name: String::new(),
+            dhcp_on,
+            ip: text(Field::Ip),
+            netmask: text(Field::Netmask),
+            gateway: text(Field::Gateway),
+            dns: text(Field::Dns),
+        }
+    }
+
+    /// the settings of `nic`, the addresses like `192.168.3.55/24` if `cidr` and every netmask
+    /// has a prefix, otherwise with the netmasks in their own box
+    pub fn from_nic(nic: &Nic, cidr: bool) -> Self {
+        let lines = |ips: &[IpAddr]| ips.iter().join("\n");
+        let cidr_lines = nic
+            .address()
+            .iter()
+            .map(|item| cidr::format_cidr(&item.ip, &item.netmask))
+            .collect::<Option<Vec<_>>>()
+            .filter(|_| cidr);
+        let (ip, netmask) = match cidr_lines {
+            Some(cidr_lines) => (cidr_lines.join("\n"), String::new()),
+            None => (
+                nic.address().iter().map(|item| item.ip).join("\n"),
+                nic.address().iter().map(|item| item.netmask).join("\n"),
+            ),
+        };
+        ProfileForm {
+            name: String::new(),
+            dhcp_on: nic.dhcp_on(),
+            ip,
+            netmask,
+            gateway: lines(nic.gateway()),
+            dns: lines(nic.dns()),
+        }
+    }
+
+    /// the settings of `profile`, or its template
+    pub fn from_profile(profile: &Profile, cidr: bool) -> Self {
+        let mut form = match profile.template() {
+            Some(template) => ProfileForm::from_texts(false, &template.texts()),
+            None => ProfileForm::from_nic(profile.nic(), cidr),
+        };
+        form.name = profile.name().to_string();
+        form
+    }
+
+    /// `ip, netmask, gateway, dns`
+    pub fn texts(&self) -> [&str; 4] {
+        [&self.ip, &self.netmask, &self.gateway, &self.dns]
+    }
+
+    /// the template of the texts, `None` if DHCP is on or there are no `{variables}`
+    pub fn template(&self) -> Option<Template> {
+        if self.dhcp_on {
+            return None;
+        }
+        Template::from_texts(&self.texts().map(str::to_string))
+    }
+
+    /// the form with the variables of the template filled by `values`,
+    /// the same form if it's not a template
+    pub fn expand(&self, values: &HashMap<String, String>) -> Result<ProfileForm> {
+        let Some(template) = self.template() else {
+            return Ok(self.clone());
+        };
+        let texts = template.expand(values)?;
+        Ok(ProfileForm {
+            name: self.name.clone(),
+            ..ProfileForm::from_texts(false, &texts)
+        })
+    }
+
+    /// parse and check the addresses, none if DHCP is on.
+    /// The errors are `Error::Validation`, pointing at the field and line
+    pub fn check(&self) -> Result<Addresses> {
+        if self.dhcp_on {
+            return Ok(Addresses::default());
+        }
+        let (ip, netmask) = validate::parse_addresses(&self.ip, &self.netmask)?;
+        let gateway = validate::parse_lines(Field::Gateway, &self.gateway)?;
+        let dns = validate::parse_lines(Field::Dns, &self.dns)?;
+        validate::check(&ip, &netmask, &gateway, &dns)?;
+
+        let values = |lines: Vec<validate::Line>| lines.into_iter().map(|(_, ip)| ip).collect_vec();
+        Ok(Addresses {
+            address: ip
+                .into_iter()
+                .zip(netmask)
+                .map(|((_, ip), (_, netmask))| Address { ip, netmask })
+                .collect(),
+            gateway: values(gateway),
+            dns: values(dns),
+        })
+    }
+
+    /// the checked settings for `adapter`, a template must be expanded first
+    pub fn to_nic(&self, adapter: &AdapterIdentity) -> Result<Nic> {
+        let (name, index, guid) = (&adapter.name, adapter.index, &adapter.guid);
+        if self.dhcp_on {
+            return Nic::new(name, index, guid, true, None, None, None).map_err(Error::settings);
+        }
+        let addresses = self.check()?;
+        Nic::new(
+            name,
+            index,
+            guid,
+            false,
+            Some(addresses.address),
+            Some(addresses.gateway),
+            Some(addresses.dns),
+        )
+        .map_err(Error::settings)
+    }
+
+    /// the settings to save for `adapter`. A template has no addresses, only its variables
+    /// are checked, the addresses are checked when it's expanded
+    pub fn to_settings(&self, adapter: &AdapterIdentity) -> Result<(Nic, Option<Template>)> {
+        let Some(template) = self.template() else {
+            return Ok((self.to_nic(adapter)?, None));
+        };
+        template.variables()?;
+        let nic = Nic::new(
+            &adapter.name,
+            adapter.index,
+            &adapter.guid,
+            false,
+            Some(vec![]),
+            Some(vec![]),
+            Some(vec![]),
+        )
+        .map_err(Error::settings)?;
+        Ok((nic, Some(template)))
+    }
+
+    /// the profile to save for `adapter`, see `to_settings`
+    pub fn to_profile(&self, adapter: &AdapterIdentity) -> Result<Profile> {
+        let (nic, template) = self.to_settings(adapter)?;
+        let mut profile = Profile::new(&self.name, nic, adapter.mac.clone());
+        profile.set_template(template);
+        profile.set_hardware(adapter.hardware.clone());
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::FieldError;
+
+    fn adapter() -> AdapterIdentity {
+        AdapterIdentity {
+            guid: "{guid}".to_string(),
+            name: "Ethernet".to_string(),
+            index: 7,
+            mac: Some("00-11-22-33-44-55".to_string()),
+            hardware: None,
+        }
+    }
+
+    fn form(ip: &str, netmask: &str, gateway: &str, dns: &str) -> ProfileForm {
+        ProfileForm::from_texts(false, &[ip, netmask, gateway, dns])
+    }
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    fn field_error(err: &Error) -> &FieldError {
+        match err {
+            Error::Validation(err) => err,
+            _ => panic!("not a validation error: {}", err),
+        }
+    }
+
+    #[test]
+    fn static_form_to_nic() {
+        let form = form(
+            "192.168.3.55\n10.0.0.2",
+            "255.255.255.0\n255.0.0.0",
+            "192.168.3.1",
+            "8.8.8.8\n114.114.114.114",
+        );
+        let nic = form.to_nic(&adapter()).unwrap();
+        assert_eq!(nic.name(), "Ethernet");
+        assert_eq!(nic.guid(), "{guid}");
+        assert_eq!(nic.index(), Some(7));
+        assert!(!nic.dhcp_on());
+        assert_eq!(
+            nic.address(),
+            &vec![
+                Address {
+                    ip: ip("192.168.3.55"),
+                    netmask: ip("255.255.255.0")
+                },
+                Address {
+                    ip: ip("10.0.0.2"),
+                    netmask: ip("255.0.0.0")
+                },
+            ]
+        );
+        assert_eq!(nic.gateway(), &vec![ip("192.168.3.1")]);
+        assert_eq!(nic.dns(), &vec![ip("8.8.8.8"), ip("114.114.114.114")]);
+    }
+
+    #[test]
+    fn blank_lines_and_spaces_are_ignored() {
+        let form = form("\n 192.168.3.55 \n\n", "255.255.255.0\n", "", "  ");
+        let addresses = form.check().unwrap();
+        assert_eq!(addresses.address.len(), 1);
+        assert!(addresses.gateway.is_empty());
+        assert!(addresses.dns.is_empty());
+    }
+
+    #[test]
+    fn cidr_line_needs_no_netmask() {
+        let form = form("192.168.3.55/24\n10.0.0.2", "255.0.0.0", "", "");
+        let addresses = form.check().unwrap();
+        assert_eq!(addresses.address[0].netmask, ip("255.255.255.0"));
+        assert_eq!(addresses.address[1].netmask, ip("255.0.0.0"));
+    }
+
+    #[test]
+    fn errors_point_at_the_field_and_line() {
+        let err = form(
+            "192.168.3.55\n192.168.3.300",
+            "255.255.255.0\n255.255.255.0",
+            "",
+            "",
+        )
+        .to_nic(&adapter())
+        .unwrap_err();
+        let err = field_error(&err);
+        assert_eq!((err.field, err.line), (Field::Ip, 2));
+
+        let err = form("192.168.3.55", "255.255.255.0", "10.0.0.1", "")
+            .to_nic(&adapter())
+            .unwrap_err();
+        let err = field_error(&err);
+        assert_eq!((err.field, err.line), (Field::Gateway, 1));
+
+        let err = form("192.168.3.55", "", "", "").check().unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Validation(FieldError {
+                field: Field::Ip,
+                line: 1,
+                ..
+            })
+        ));
+
+        let err = form("192.168.3.55", "255.255.255.0\n255.0.0.0", "", "")
+            .check()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Validation(FieldError {
+                field: Field::Netmask,
+                line: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn dhcp_ignores_the_texts() {
+        let form = ProfileForm::from_texts(true, &["not an address", "", "", ""]);
+        assert_eq!(form.check().unwrap(), Addresses::default());
+        assert!(form.template().is_none());
+        let nic = form.to_nic(&adapter()).unwrap();
+        assert!(nic.dhcp_on());
+        assert!(nic.address().is_empty());
+    }
+
+    #[test]
+    fn missing_texts_are_empty() {
+        let form = ProfileForm::from_texts(false, &["192.168.3.55/24"]);
+        assert_eq!(form.texts(), ["192.168.3.55/24", "", "", ""]);
+        assert!(form.check().is_ok());
+    }
+
+    #[test]
+    fn nic_round_trip() {
+        let nic = form(
+            "192.168.3.55\n10.0.0.2",
+            "255.255.255.0\n255.0.0.0",
+            "192.168.3.1",
+            "8.8.8.8",
+        )
+        .to_nic(&adapter())
+        .unwrap();
+
+        let dotted = ProfileForm::from_nic(&nic, false);
+        assert_eq!(dotted.ip, "192.168.3.55\n10.0.0.2");
+        assert_eq!(dotted.netmask, "255.255.255.0\n255.0.0.0");
+        assert_eq!(dotted.gateway, "192.168.3.1");
+        assert_eq!(dotted.dns, "8.8.8.8");
+        assert_eq!(dotted.to_nic(&adapter()).unwrap(), nic);
+
+        let cidr = ProfileForm::from_nic(&nic, true);
+        assert_eq!(cidr.ip, "192.168.3.55/24\n10.0.0.2/8");
+        assert_eq!(cidr.netmask, "");
+        assert_eq!(cidr.to_nic(&adapter()).unwrap(), nic);
+    }
+
+    #[test]
+    fn netmask_without_prefix_keeps_its_box() {
+        let address = Address {
+            ip: ip("192.168.3.55"),
+            netmask: ip("255.0.255.0"),
+        };
+        let nic = Nic::new(
+            "Ethernet",
+            7,
+            "{guid}",
+            false,
+            Some(vec![address]),
+            None,
+            None,
+        )
+        .unwrap();
+
+        let form = ProfileForm::from_nic(&nic, true);
+        assert_eq!(form.ip, "192.168.3.55");
+        assert_eq!(form.netmask, "255.0.255.0");
+        let err = form.to_nic(&adapter()).unwrap_err();
+        let err = field_error(&err);
+        assert_eq!((err.field, err.line), (Field::Netmask, 1));
+    }
+
+    #[test]
+    fn template_form() {
+        let mut form = form("10.20.{site}.50", "255.255.255.0", "10.20.{site}.1", "");
+        form.name = "Site".to_string();
+        let (nic, template) = form.to_settings(&adapter()).unwrap();
+        assert!(nic.address().is_empty());
+        assert_eq!(template.unwrap().variables().unwrap(), vec!["site"]);
+
+        // a template can't be applied before it's expanded
+        assert!(form.to_nic(&adapter()).is_err());
+
+        let values = HashMap::from([("site".to_string(), "3".to_string())]);
+        let expanded = form.expand(&values).unwrap();
+        assert_eq!(expanded.name, "Site");
+        assert_eq!(expanded.ip, "10.20.3.50");
+        assert!(expanded.template().is_none());
+        assert!(expanded.to_nic(&adapter()).is_ok());
+    }
+
+    #[test]
+    fn template_with_bad_variables_is_rejected() {
+        let form = form("10.20.{si te}.50", "255.255.255.0", "", "");
+        assert!(matches!(
+            form.to_settings(&adapter()),
+            Err(Error::Template(message)) if message == "`{si te}` is not a valid variable"
+        ));
+        assert!(matches!(
+            form.expand(&HashMap::new()),
+            Err(Error::Template(_))
+        ));
+    }
+
+    #[test]
+    fn expand_without_template_is_the_same() {
+        let form = form("192.168.3.55/24", "", "", "");
+        assert_eq!(form.expand(&HashMap::new()).unwrap(), form);
+    }
+
+    #[test]
+    fn profile_round_trip() {
+        let mut form = form("192.168.3.55/24", "", "192.168.3.1", "");
+        form.name = " Office ".to_string();
+        let profile = form.to_profile(&adapter()).unwrap();
+        assert_eq!(profile.name(), "Office");
+        assert!(profile.template().is_none());
+
+        let back = ProfileForm::from_profile(&profile, true);
+        assert_eq!(back.name, "Office");
+        assert_eq!(back.texts(), ["192.168.3.55/24", "", "192.168.3.1", ""]);
+    }
+
+    #[test]
+    fn profile_without_name_gets_a_default() {
+        let profile = ProfileForm::from_texts(true, &[""; 4])
+            .to_profile(&adapter())
+            .unwrap();
+        assert_eq!(profile.name(), "Ethernet - dhcp");
+    }
+
+    #[test]
+    fn template_profile_round_trip() {
+        let mut form = form("10.20.{site}.50", "255.255.255.0", "", "");
+        form.name = "Site".to_string();
+        let profile = form.to_profile(&adapter()).unwrap();
+        assert!(profile.template().is_some());
+        assert_eq!(ProfileForm::from_profile(&profile, false), form);
+    }
+}
@@ -0,0 +1,1532 @@
+//! The window, it's built with the `gui` feature.
+
+use cfg_ip::backend::{Backend, System};
+use cfg_ip::binding::{self, AdapterBinding, AdapterIdentity, Hardware};
+use cfg_ip::config::ConfigFile;
+use cfg_ip::form::ProfileForm;
+use cfg_ip::history::{self, History};
+use cfg_ip::media::{AdapterFilter, MediaType};
+use cfg_ip::overlap;
+use cfg_ip::scene;
+use cfg_ip::search::Query;
+use cfg_ip::store::{ConflictPolicy, GroupFilter, Scene};
+use cfg_ip::subnet::Subnet;
+use cfg_ip::template::Template;
+use cfg_ip::utils;
+use cfg_ip::validate;
+use cfg_ip::watch::{self, Notifier};
+use itertools::Itertools;
+use net_adapters::adapter::Nic;
+use slint::{Model, ModelRc, SharedString, VecModel};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub use cfg_ip::ui::generated_code::*;
+
+/// how often the config file and the adapters are checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+type LocalAdapters = Rc<RefCell<Vec<AdapterIdentity>>>;
+/// the adapters read by the last refresh, the list shows the ones matching the search
+type SystemNics = Rc<RefCell<Vec<Nic>>>;
+type SharedHistory = Rc<RefCell<History>>;
+/// where the adapters are read from and the settings applied, a fake in the tests
+type SharedBackend = Rc<dyn Backend>;
+/// the action to run when the user accepts the confirm box
+type PendingAction = Rc<RefCell<Option<Box<dyn FnOnce(&Main)>>>>;
+type SharedHardware = Rc<RefCell<HardwareCache>>;
+
+/// The cards of the adapters. The query takes a while, so it runs on another thread,
+/// and the last cards known are used until it's done
+#[derive(Default)]
+struct HardwareCache {
+    known: HashMap<String, Hardware>,
+    running: Option<Receiver<HashMap<String, Hardware>>>,
+}
+
+impl HardwareCache {
+    /// query the cards again, unless a query is running
+    fn refresh(&mut self, backend: &SharedBackend) {
+        if self.running.is_some() {
+            return;
+        }
+        let query = backend.hardware();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || sender.send(query()));
+        self.running = Some(receiver);
+    }
+
+    /// take the result of the query if it's done, returns whether the cards changed
+    fn poll(&mut self) -> bool {
+        let Some(running) = &self.running else {
+            return false;
+        };
+        match running.try_recv() {
+            Ok(hardware) => {
+                self.running = None;
+                let changed = hardware != self.known;
+                self.known = hardware;
+                changed
+            }
+            Err(TryRecvError::Disconnected) => {
+                self.running = None;
+                false
+            }
+            Err(TryRecvError::Empty) => false,
+        }
+    }
+
+    fn identify(&self, adapters: &[Nic]) -> Vec<AdapterIdentity> {
+        binding::identify(adapters, &self.known)
+    }
+}
+
+/// open the window with the saved items of `config_path`, until it's closed
+pub fn run(config_path: &Path) {
+    let window = Main::new().unwrap();
+    let version = env!("CARGO_PKG_VERSION");
+    let title = window.get_window_title();
+    window.set_window_title(format!("{} v{}", title, version).into());
+    let _watcher = setup(&window, Rc::new(System), config_path);
+    window.run().unwrap();
+}
+
+/// load the config file and the history, and wire the callbacks of `window`.
+/// The saved items are reloaded by the timer returned while it's kept
+fn setup(window: &Main, backend: SharedBackend, config_path: &Path) -> slint::Timer {
+    let cfg = match ConfigFile::load(config_path) {
+        Ok((cfg, None)) => cfg,
+        Ok((cfg, Some(recovery))) => {
+            show_message_box(
+                window,
+                "Warning",
+                &format!(
+                    "The file {} is broken: {}\nIt's kept as {}, and {} saved settings are recovered from it.",
+                    config_path.display(),
+                    recovery.error,
+                    recovery.backup.display(),
+                    recovery.recovered
+                ),
+            );
+            cfg
+        }
+        Err(err) => {
+            show_message_box(
+                window,
+                "Warning",
+                &format!("Failed to load {}: {}", config_path.display(), err),
+            );
+            ConfigFile::empty(config_path)
+        }
+    };
+    let cfg = Arc::new(Mutex::new(cfg));
+    let adapters = LocalAdapters::default();
+    let nics = SystemNics::default();
+    let hardware = SharedHardware::default();
+    let pending = PendingAction::default();
+    let history_path = History::path_for(config_path);
+    let history = match History::load(&history_path) {
+        Ok(history) => history,
+        Err(err) => {
+            show_message_box(
+                window,
+                "Warning",
+                &format!("Failed to load {}: {}", history_path.display(), err),
+            );
+            History::empty(&history_path)
+        }
+    };
+    let history = Rc::new(RefCell::new(history));
+
+    show_adapter_filter(window, &cfg.lock().unwrap().adapter_filter);
+    refresh_adapters(window, &backend, &adapters, &nics, &hardware);
+    load_history_items(window, &history);
+    load_saved_items(window.as_weak(), cfg.clone(), &adapters);
+    utils::set_ui_checker(window);
+    utils::set_item_convert(window);
+
+    set_apply_config(
+        window,
+        backend.clone(),
+        cfg.clone(),
+        adapters.clone(),
+        hardware.clone(),
+        history.clone(),
+        pending.clone(),
+    );
+    set_scenes(
+        window,
+        backend.clone(),
+        cfg.clone(),
+        adapters.clone(),
+        hardware.clone(),
+        history.clone(),
+        pending.clone(),
+    );
+    set_history(window, backend.clone(), history, pending.clone());
+    set_subnet_calculator(window);
+    set_save_config(window, cfg.clone(), adapters.clone());
+    set_update_selected(window, cfg.clone(), adapters.clone());
+    set_remove_selected(window, cfg.clone(), adapters.clone());
+    set_transfer(window, cfg.clone(), adapters.clone());
+    set_groups(window, cfg.clone(), adapters.clone());
+    set_load_selected(window, pending.clone());
+    set_adapter_filter(window, cfg.clone(), adapters.clone(), nics.clone());
+
+    // reload the saved items when the config file is changed by hand or by another instance,
+    // and the adapters when they change
+    let watcher = slint::Timer::default();
+    watcher.start(slint::TimerMode::Repeated, WATCH_INTERVAL, {
+        let weak = window.as_weak();
+        let backend = backend.clone();
+        let cfg = cfg.clone();
+        let adapters = adapters.clone();
+        let nics = nics.clone();
+        let hardware = hardware.clone();
+        let mut notifier = Notifier::system();
+        move || {
+            let window = weak.unwrap();
+            let changed = poll_adapters(&window, &backend, &adapters, &nics, &hardware, &mut notifier);
+            // the cards arrived, the adapters are matched and typed by them
+            let identified = hardware.borrow_mut().poll();
+            if identified {
+                let current = nics.borrow().clone();
+                update_adapters(&window, &adapters, &nics, current, &hardware.borrow());
+            }
+            if changed || identified {
+                load_saved_items(weak.clone(), cfg.clone(), &adapters);
+            }
+            let reloaded = cfg.lock().unwrap().reload();
+            match reloaded {
+                Ok(true) => {
+                    show_adapter_filter(&window, &cfg.lock().unwrap().adapter_filter);
+                    show_adapters(&window, &nics.borrow(), &adapters.borrow());
+                    load_saved_items(weak.clone(), cfg.clone(), &adapters);
+                }
+                Ok(false) => {}
+                Err(err) => show_message_box(
+                    &window,
+                    "Warning",
+                    &format!(
+                        "The config file is changed, but it can't be loaded: {}\nThe saved settings shown are kept.",
+                        err
+                    ),
+                ),
+            }
+        }
+    });
+
+    window.on_search_changed({
+        let weak = window.as_weak();
+        let cfg = cfg.clone();
+        let adapters = adapters.clone();
+        let nics = nics.clone();
+        move || {
+            show_adapters(&weak.unwrap(), &nics.borrow(), &adapters.borrow());
+            load_saved_items(weak.clone(), cfg.clone(), &adapters);
+        }
+    });
+
+    window.on_refresh_adapters({
+        let window = window.as_weak();
+        move || {
+            refresh_adapters(&window.unwrap(), &backend, &adapters, &nics, &hardware);
+            load_saved_items(window.clone(), cfg.clone(), &adapters);
+        }
+    });
+
+    let run_pending = {
+        let weak = window.as_weak();
+        move || {
+            let action = pending.borrow_mut().take();
+            if let Some(action) = action {
+                action(&weak.unwrap());
+            }
+        }
+    };
+    window.on_confirm_accepted(run_pending.clone());
+    window.on_variables_accepted(run_pending);
+
+    watcher
+}
+
+fn set_remove_selected(window: &Main, cfg: Arc<Mutex<ConfigFile>>, adapters: LocalAdapters) {
+    window.on_remove_selected({
+        let weak = window.as_weak();
+        move |index| {
+            if index < 0 {
+                return;
+            }
+            let Some(name) = saved_name(&weak.unwrap(), index) else {
+                return;
+            };
+            let result = cfg.lock().unwrap().modify(|cfg| {
+                if let Some(i) = cfg.items.position(&name) {
+                    cfg.items.remove_at(i as i32);
+                }
+            });
+            match result {
+                Ok(_) => load_saved_items(weak.clone(), cfg.clone(), &adapters),
+                Err(err) => show_message_box(&weak.unwrap(), "Warning", &err.to_string()),
+            }
+        }
+    });
+}
+
+fn set_transfer(window: &Main, cfg: Arc<Mutex<ConfigFile>>, adapters: LocalAdapters) {
+    window.on_export_profiles({
+        let weak = window.as_weak();
+        let cfg = cfg.clone();
+        move |path| {
+            let window = weak.unwrap();
+            let checked = window
+                .global::<NetInterfaceStatus>()
+                .get_export_items()
+                .iter()
+                .map(|item| item.checked)
+                .collect_vec();
+            let result = {
+                let cfg = cfg.lock().unwrap();
+                let profiles = cfg
+                    .items
+                    .profiles()
+                    .iter()
+                    .zip(&checked)
+                    .filter_map(|(item, checked)| checked.then_some(item))
+                    .collect_vec();
+                if profiles.is_empty() {
+                    show_message_box(&window, "Warning", "Check the saved settings to export.");
+                    return;
+                }
+                cfg_ip::transfer::export_profiles(path.as_str(), profiles.iter().copied())
+                    .map(|_| profiles.len())
+            };
+            match result {
+                Ok(count) => show_message_box(
+                    &window,
+                    "Export",
+                    &format!("{} saved settings are exported to {}", count, path),
+                ),
+                Err(err) => show_message_box(&window, "Warning", &err.to_string()),
+            }
+        }
+    });
+
+    window.on_import_profiles({
+        let weak = window.as_weak();
+        move |path, policy| {
+            let window = weak.unwrap();
+            let profiles = match cfg_ip::transfer::import_profiles(path.as_str()) {
+                Ok(profiles) => profiles,
+                Err(err) => {
+                    show_message_box(&window, "Warning", &err.to_string());
+                    return;
+                }
+            };
+            let policy = match policy {
+                1 => ConflictPolicy::Skip,
+                2 => ConflictPolicy::Replace,
+                _ => ConflictPolicy::Merge,
+            };
+            let report = cfg
+                .lock()
+                .unwrap()
+                .modify(|cfg| cfg.items.import(profiles, policy));
+            let report = match report {
+                Ok(report) => report,
+                Err(err) => {
+                    show_message_box(&window, "Warning", &err.to_string());
+                    return;
+                }
+            };
+            load_saved_items(weak.clone(), cfg.clone(), &adapters);
+            show_message_box(
+                &window,
+                "Import",
+                &format!(
+                    "added {}, renamed {}, replaced {}, skipped {}",
+                    report.added, report.renamed, report.replaced, report.skipped
+                ),
+            );
+        }
+    });
+}
+
+fn set_groups(window: &Main, cfg: Arc<Mutex<ConfigFile>>, adapters: LocalAdapters) {
+    window.on_filter_saved({
+        let weak = window.as_weak();
+        let cfg = cfg.clone();
+        let adapters = adapters.clone();
+        move || load_saved_items(weak.clone(), cfg.clone(), &adapters)
+    });
+
+    window.on_move_to_group({
+        let weak = window.as_weak();
+        let cfg = cfg.clone();
+        let adapters = adapters.clone();
+        move |index, group| {
+            let window = weak.unwrap();
+            let Some(name) = saved_name(&window, index) else {
+                return;
+            };
+            let result = cfg
+                .lock()
+                .unwrap()
+                .modify(|cfg| cfg.items.move_to_group(&name, Some(&group)));
+            match result {
+                Ok(_) => load_saved_items(weak.clone(), cfg.clone(), &adapters),
+                Err(err) => show_message_box(&window, "Warning", &err.to_string()),
+            }
+        }
+    });
+
+    window.on_remove_group({
+        let weak = window.as_weak();
+        move || {
+            let window = weak.unwrap();
+            let GroupFilter::Group(group) = group_filter(&window) else {
+                show_message_box(&window, "Warning", "Choose a group to remove.");
+                return;
+            };
+            let result = cfg
+                .lock()
+                .unwrap()
+                .modify(|cfg| cfg.items.remove_group(&group));
+            match result {
+                Ok(_) => load_saved_items(weak.clone(), cfg.clone(), &adapters),
+                Err(err) => show_message_box(&window, "Warning", &err.to_string()),
+            }
+        }
+    });
+}
+
+fn set_apply_config(
+    window: &Main,
+    backend: SharedBackend,
+    cfg: Arc<Mutex<ConfigFile>>,
+    adapters: LocalAdapters,
+    hardware: SharedHardware,
+    history: SharedHistory,
+    pending: PendingAction,
+) {
+    window.on_apply_config({
+        let weak_window = window.as_weak();
+        move |_item, _target, _dhcp_on, _texts| {
+            let window = weak_window.unwrap();
+            let form = utils::form_of(_dhcp_on, &_texts);
+            if let Some(template) = form.template() {
+                // apply again with the expanded texts
+                ask_variables(&window, &pending, template, move |window, texts| {
+                    window.invoke_apply_config(
+                        _item,
+                        _target,
+                        _dhcp_on,
+                        utils::create_model_vec(texts),
+                    )
+                });
+                return;
+            }
+
+            let current = backend.adapters();
+            *adapters.borrow_mut() = hardware.borrow().identify(&current);
+            // the binding of the saved profile loaded, not of another one from the same adapter
+            let binding = cfg
+                .lock()
+                .unwrap()
+                .items
+                .find_by_name(&_item.profile)
+                .map(|profile| profile.binding().clone());
+            let is_saved = binding.is_some();
+            let binding = binding.unwrap_or_else(|| AdapterBinding::new(&_item.name, None));
+
+            let (target, orphaned) = {
+                let adapters = adapters.borrow();
+                let resolved = binding.resolve(&_item.guid, &adapters).map(|(item, _)| item);
+                let target = if _target > 0 {
+                    // picked in the `adapter` box
+                    let choices = window.global::<NetInterfaceStatus>().get_adapter_choices();
+                    let name = choices.row_data(_target as usize).unwrap_or_default();
+                    adapters.iter().find(|item| item.name == name.as_str())
+                } else {
+                    resolved
+                };
+                let Some(target) = target else {
+                    show_message_box(
+                        &window,
+                        "Warning",
+                        &format!(
+                            "No adapter on this machine matches `{}`. Choose one in the `adapter` box.",
+                            _item.name
+                        ),
+                    );
+                    return;
+                };
+                // offer to re-bind the saved profiles of an adapter that can't be found
+                let orphaned = is_saved && resolved.is_none();
+                (target.clone(), orphaned)
+            };
+
+            let nic = match form.to_nic(&target) {
+                Ok(nic) => nic,
+                Err(err) => {
+                    show_edit_error(&window, &_texts, &err);
+                    return;
+                }
+            };
+            let conflicts = overlap::find(std::slice::from_ref(&nic), &current);
+            let apply = {
+                let backend = backend.clone();
+                let history = history.clone();
+                let cfg = cfg.clone();
+                let adapters = adapters.clone();
+                let pending = pending.clone();
+                move |window: &Main| {
+                    let _msg = history.borrow_mut().apply(
+                        backend.as_ref(),
+                        &nic,
+                        Some(&_item.profile),
+                        &current,
+                    );
+                    load_history_items(window, &history);
+                    let _msg = match _msg {
+                        Err(err) => {
+                            show_message_box(window, "Warning", &err.to_string());
+                            return;
+                        }
+                        Ok(msg) => msg,
+                    };
+
+                    #[cfg(debug_assertions)]
+                    println!("{}", _msg);
+
+                    if orphaned {
+                        let old_guid = _item.guid.to_string();
+                        show_confirm_box(
+                            window,
+                            &pending,
+                            "Re-bind",
+                            &format!(
+                                "The adapter of the saved profiles `{}` is not found on this machine. Re-bind them to `{}`?",
+                                _item.name, target.name
+                            ),
+                            move |window| {
+                                let result = cfg
+                                    .lock()
+                                    .unwrap()
+                                    .modify(|cfg| cfg.items.rebind(&old_guid, &target))
+                                    .and_then(|result| result);
+                                match result {
+                                    Ok(_) => load_saved_items(window.as_weak(), cfg, &adapters),
+                                    Err(err) => {
+                                        show_message_box(window, "Warning", &err.to_string())
+                                    }
+                                }
+                            },
+                        );
+                    }
+                }
+            };
+            match overlap_warning(&conflicts) {
+                Some(text) => show_confirm_box(&window, &pending, "Overlap", &text, apply),
+                None => apply(&window),
+            }
+        }
+    });
+}
+
+fn set_scenes(
+    window: &Main,
+    backend: SharedBackend,
+    cfg: Arc<Mutex<ConfigFile>>,
+    adapters: LocalAdapters,
+    hardware: SharedHardware,
+    history: SharedHistory,
+    pending: PendingAction,
+) {
+    // the name of the scene at `row` of the list
+    let scene_name = |window: &Main, row: i32| {
+        let item = window
+            .global::<NetInterfaceStatus>()
+            .get_scene_names()
+            .row_data(usize::try_from(row).ok()?)?;
+        Some(item.text.to_string())
+    };
+
+    window.on_scene_selected({
+        let weak = window.as_weak();
+        let cfg = cfg.clone();
+        move |row| {
+            let window = weak.unwrap();
+            let scene = scene_name(&window, row)
+                .and_then(|name| cfg.lock().unwrap().items.find_scene(&name).cloned());
+            let profiles = window.global::<NetInterfaceStatus>().get_scene_profiles();
+            for i in 0..profiles.row_count() {
+                let mut item = profiles.row_data(i).unwrap();
+                item.checked = scene
+                    .as_ref()
+                    .is_some_and(|scene| scene.profiles.iter().any(|name| item.name == *name));
+                profiles.set_row_data(i, item);
+            }
+        }
+    });
+
+    window.on_save_scene({
+        let weak = window.as_weak();
+        let cfg = cfg.clone();
+        let adapters = adapters.clone();
+        move |name| {
+            let window = weak.unwrap();
+            let profiles = window
+                .global::<NetInterfaceStatus>()
+                .get_scene_profiles()
+                .iter()
+                .filter(|item| item.checked)
+                .map(|item| item.name.to_string())
+                .collect_vec();
+            if profiles.is_empty() {
+                show_message_box(&window, "Warning", "Check the profiles of the scene.");
+                return;
+            }
+            let scene = Scene {
+                name: name.to_string(),
+                profiles,
+            };
+            let result = cfg
+                .lock()
+                .unwrap()
+                .modify(|cfg| cfg.items.save_scene(scene));
+            match result {
+                Ok(true) => load_saved_items(weak.clone(), cfg.clone(), &adapters),
+                Ok(false) => show_message_box(&window, "Warning", "Fill the name of the scene."),
+                Err(err) => show_message_box(&window, "Warning", &err.to_string()),
+            }
+        }
+    });
+
+    window.on_remove_scene({
+        let weak = window.as_weak();
+        let cfg = cfg.clone();
+        let adapters = adapters.clone();
+        move |row| {
+            let window = weak.unwrap();
+            let Some(name) = scene_name(&window, row) else {
+                return;
+            };
+            let result = cfg
+                .lock()
+                .unwrap()
+                .modify(|cfg| cfg.items.remove_scene(&name));
+            match result {
+                Ok(_) => load_saved_items(weak.clone(), cfg.clone(), &adapters),
+                Err(err) => show_message_box(&window, "Warning", &err.to_string()),
+            }
+        }
+    });
+
+    window.on_apply_scene({
+        let weak = window.as_weak();
+        move |row| {
+            let window = weak.unwrap();
+            let Some(name) = scene_name(&window, row) else {
+                return;
+            };
+            let (scene, items) = {
+                let cfg = cfg.lock().unwrap();
+                (cfg.items.find_scene(&name).cloned(), cfg.items.clone())
+            };
+            let Some(scene) = scene else {
+                return;
+            };
+
+            let current = backend.adapters();
+            *adapters.borrow_mut() = hardware.borrow().identify(&current);
+            // a scene that can't be planned fails in `scene::apply` with the reason
+            let conflicts = scene::plan(&scene, &items, &adapters.borrow())
+                .map(|steps| {
+                    let nics = steps.into_iter().map(|(_, nic)| nic).collect_vec();
+                    overlap::find(&nics, &current)
+                })
+                .unwrap_or_default();
+            let apply = {
+                let backend = backend.clone();
+                let adapters = adapters.clone();
+                let history = history.clone();
+                move |window: &Main| {
+                    let report = scene::apply(
+                        backend.as_ref(),
+                        &scene,
+                        &items,
+                        &adapters.borrow(),
+                        &current,
+                        &mut history.borrow_mut(),
+                    );
+                    load_history_items(window, &history);
+                    let report = match report {
+                        Ok(report) => report,
+                        Err(err) => {
+                            show_message_box(window, "Warning", &err.to_string());
+                            return;
+                        }
+                    };
+
+                    let mut lines = report
+                        .steps
+                        .iter()
+                        .map(|step| match &step.error {
+                            None => format!("{}: {}, ok", step.adapter, step.profile),
+                            Some(error) => {
+                                format!("{}: {}, failed: {}", step.adapter, step.profile, error)
+                            }
+                        })
+                        .collect_vec();
+                    if !report.rollback.is_empty() {
+                        lines.push("Rolled back:".to_string());
+                        lines.extend(report.rollback.iter().map(|item| match &item.error {
+                            None => format!("{}: ok", item.adapter),
+                            Some(error) => format!("{}: failed: {}", item.adapter, error),
+                        }));
+                    }
+                    if let Some(error) = &report.history_error {
+                        lines.push(format!("The history is not recorded: {}", error));
+                    }
+                    let title = match report.is_ok() {
+                        true => "Scene applied",
+                        false => "Scene failed",
+                    };
+                    show_message_box(window, title, &lines.join("\n"));
+                }
+            };
+            match overlap_warning(&conflicts) {
+                Some(text) => show_confirm_box(&window, &pending, "Overlap", &text, apply),
+                None => apply(&window),
+            }
+        }
+    });
+}
+
+fn set_history(
+    window: &Main,
+    backend: SharedBackend,
+    history: SharedHistory,
+    pending: PendingAction,
+) {
+    // the rows of the history view are the newest first
+    let entry_at = |history: &SharedHistory, row: i32| {
+        let history = history.borrow();
+        let entries = history.entries();
+        let row = usize::try_from(row).ok()?;
+        entries.iter().rev().nth(row).cloned()
+    };
+
+    window.on_undo_apply({
+        let weak = window.as_weak();
+        let backend = backend.clone();
+        let history = history.clone();
+        let pending = pending.clone();
+        move || {
+            let window = weak.unwrap();
+            let entry = history.borrow().undo_entry().cloned();
+            match entry {
+                Some(entry) => restore_entry(&window, &backend, &history, &pending, entry, true),
+                None => show_message_box(&window, "Undo", "Nothing is left to undo."),
+            }
+        }
+    });
+
+    window.on_restore_history({
+        let weak = window.as_weak();
+        let backend = backend.clone();
+        let history = history.clone();
+        let pending = pending.clone();
+        move |row| {
+            if let Some(entry) = entry_at(&history, row) {
+                restore_entry(&weak.unwrap(), &backend, &history, &pending, entry, false);
+            }
+        }
+    });
+
+    window.on_reapply_history({
+        let weak = window.as_weak();
+        move |row| {
+            let Some(entry) = entry_at(&history, row) else {
+                return;
+            };
+            confirm_apply_state(
+                &weak.unwrap(),
+                &backend,
+                &history,
+                &pending,
+                "Apply again",
+                entry.applied,
+                false,
+            );
+        }
+    });
+}
+
+/// what a row of the subnet calculator fills into the edit pane
+enum SubnetFill {
+    /// an address with the netmask of the subnet
+    Address(IpAddr, Subnet),
+    Netmask(IpAddr),
+    Gateway(IpAddr),
+}
+
+fn set_subnet_calculator(window: &Main) {
+    let fills = Rc::new(RefCell::new(Vec::<Option<SubnetFill>>::new()));
+
+    window.on_calculate_subnet({
+        let weak = window.as_weak();
+        let fills = fills.clone();
+        move |address, mask, gateway| {
+            let window = weak.unwrap();
+            let rows = Subnet::parse(&address, &mask).and_then(|subnet| {
+                let gateway = match gateway.trim() {
+                    "" => None,
+                    text => Some(text.parse::<IpAddr>().map_err(|_| {
+                        cfg_ip::Error::Settings(format!("`{}` is not an address", text))
+                    })?),
+                };
+                Ok(subnet_rows(&subnet, gateway))
+            });
+            let rows = rows.unwrap_or_else(|err| {
+                show_message_box(&window, "Warning", &err.to_string());
+                Vec::new()
+            });
+
+            let items = rows
+                .iter()
+                .map(|(text, _)| slint::StandardListViewItem::from(text.as_str()))
+                .collect_vec();
+            *fills.borrow_mut() = rows.into_iter().map(|(_, fill)| fill).collect();
+            window
+                .global::<NetInterfaceStatus>()
+                .set_subnet_rows(ModelRc::new(VecModel::from(items)));
+        }
+    });
+
+    window.on_subnet_row_selected({
+        let weak = window.as_weak();
+        move |row, texts| {
+            let window = weak.unwrap();
+            let fills = fills.borrow();
+            let Some(Some(fill)) = usize::try_from(row).ok().and_then(|row| fills.get(row)) else {
+                return;
+            };
+            let mut form = utils::form_of(false, &texts);
+            match fill {
+                SubnetFill::Address(ip, subnet) => {
+                    let cidr = window.global::<NetInterfaceStatus>().get_show_cidr();
+                    (form.ip, form.netmask) = match cidr {
+                        true => (format!("{}/{}", ip, subnet.prefix()), String::new()),
+                        false => (ip.to_string(), subnet.netmask().to_string()),
+                    };
+                }
+                SubnetFill::Netmask(netmask) => form.netmask = netmask.to_string(),
+                SubnetFill::Gateway(gateway) => form.gateway = gateway.to_string(),
+            }
+            window.invoke_fill_edit(utils::texts_of_form(&form));
+        }
+    });
+}
+
+/// the rows of the subnet calculator, and what they fill into the edit pane
+fn subnet_rows(subnet: &Subnet, gateway: Option<IpAddr>) -> Vec<(String, Option<SubnetFill>)> {
+    let (first, last) = subnet.hosts();
+    let mut rows = vec![
+        (
+            format!("address: {}/{}", subnet.address(), subnet.prefix()),
+            Some(SubnetFill::Address(subnet.address(), *subnet)),
+        ),
+        (
+            format!("netmask: {}", subnet.netmask()),
+            Some(SubnetFill::Netmask(subnet.netmask())),
+        ),
+        (
+            format!("network: {}/{}", subnet.network(), subnet.prefix()),
+            None,
+        ),
+    ];
+    if let Some(broadcast) = subnet.broadcast() {
+        rows.push((format!("broadcast: {}", broadcast), None));
+    }
+    rows.push((
+        format!("first host: {}", first),
+        Some(SubnetFill::Address(first, *subnet)),
+    ));
+    rows.push((
+        format!("last host: {}", last),
+        Some(SubnetFill::Address(last, *subnet)),
+    ));
+    rows.push((format!("hosts: {}", subnet.host_count()), None));
+    if let Some(gateway) = gateway {
+        let row = match subnet.contains(&gateway) {
+            true => (
+                format!("gateway: {} is inside the subnet", gateway),
+                Some(SubnetFill::Gateway(gateway)),
+            ),
+            false => (format!("gateway: {} is outside the subnet", gateway), None),
+        };
+        rows.push(row);
+    }
+    rows
+}
+
+/// apply the settings of the adapter before `entry`
+fn restore_entry(
+    window: &Main,
+    backend: &SharedBackend,
+    history: &SharedHistory,
+    pending: &PendingAction,
+    entry: history::HistoryEntry,
+    undo: bool,
+) {
+    let title = match undo {
+        true => "Undo",
+        false => "Restore",
+    };
+    match entry.previous {
+        Some(previous) => {
+            confirm_apply_state(window, backend, history, pending, title, previous, undo)
+        }
+        None => show_message_box(
+            window,
+            "Warning",
+            &format!(
+                "The settings of `{}` before {} are unknown.",
+                entry.adapter, entry.time
+            ),
+        ),
+    }
+}
+
+/// apply `state` from the history after the user confirms, recorded as an undo if `undo`,
+/// the adapter is found by guid, in case it's renamed
+fn confirm_apply_state(
+    window: &Main,
+    backend: &SharedBackend,
+    history: &SharedHistory,
+    pending: &PendingAction,
+    title: &str,
+    state: Nic,
+    undo: bool,
+) {
+    let current = backend.adapters();
+    let Some(adapter) = current.iter().find(|item| item.guid() == state.guid()) else {
+        show_message_box(
+            window,
+            "Warning",
+            &format!(
+                "The adapter `{}` is not found on this machine.",
+                state.name()
+            ),
+        );
+        return;
+    };
+    let nic = match Nic::new(
+        adapter.name(),
+        adapter.index().unwrap_or_default(),
+        adapter.guid(),
+        state.dhcp_on(),
+        Some(state.address().to_vec()),
+        Some(state.gateway().to_vec()),
+        Some(state.dns().to_vec()),
+    ) {
+        Ok(nic) => nic,
+        Err(err) => {
+            show_message_box(window, "Warning", &err.to_string());
+            return;
+        }
+    };
+
+    let mut text = format!("Apply `{}` to `{}`?", history::summary(&nic), nic.name());
+    if let Some(warning) = overlap_warning(&overlap::find(std::slice::from_ref(&nic), &current)) {
+        text = format!("{}\n\n{}", text, warning);
+    }
+    show_confirm_box(window, pending, title, &text, {
+        let backend = backend.clone();
+        let history = history.clone();
+        move |window| {
+            let result = match undo {
+                true => history.borrow_mut().undo(backend.as_ref(), &nic, &current),
+                false => history
+                    .borrow_mut()
+                    .apply(backend.as_ref(), &nic, None, &current),
+            };
+            load_history_items(window, &history);
+            match result {
+                Ok(_msg) => {
+                    #[cfg(debug_assertions)]
+                    println!("{}", _msg);
+                }
+                Err(err) => show_message_box(window, "Warning", &err.to_string()),
+            }
+        }
+    });
+}
+
+/// show or hide the types of adapters, the choice is kept in the config file
+fn set_adapter_filter(
+    window: &Main,
+    cfg: Arc<Mutex<ConfigFile>>,
+    adapters: LocalAdapters,
+    nics: SystemNics,
+) {
+    window.on_adapter_filter_changed({
+        let weak = window.as_weak();
+        move || {
+            let window = weak.unwrap();
+            let filter = adapter_filter(&window);
+            show_adapters(&window, &nics.borrow(), &adapters.borrow());
+            let result = cfg
+                .lock()
+                .unwrap()
+                .modify(|cfg| cfg.adapter_filter = filter);
+            if let Err(err) = result {
+                show_message_box(&window, "Warning", &err.to_string());
+            }
+        }
+    });
+}
+
+fn set_save_config(window: &Main, cfg: Arc<Mutex<ConfigFile>>, adapters: LocalAdapters) {
+    window.on_save_config({
+        let weak = window.as_weak();
+        move |_item, _profile_name, _dhcp_on, _texts| {
+            let window = weak.unwrap();
+            let known = adapters
+                .borrow()
+                .iter()
+                .find(|item| item.guid == _item.guid.as_str())
+                .cloned();
+            let adapter = AdapterIdentity {
+                guid: _item.guid.to_string(),
+                name: _item.name.to_string(),
+                index: _item.index as u32,
+                mac: known.as_ref().and_then(|item| item.mac.clone()),
+                hardware: known.and_then(|item| item.hardware),
+            };
+            let mut form = utils::form_of(_dhcp_on, &_texts);
+            form.name = _profile_name.to_string();
+            let mut profile = match form.to_profile(&adapter) {
+                Ok(profile) => profile,
+                Err(err) => {
+                    show_edit_error(&window, &_texts, &err);
+                    return;
+                }
+            };
+            // saved to the group shown
+            if let GroupFilter::Group(group) = group_filter(&window) {
+                profile.set_group(Some(&group));
+            }
+            let result = cfg.lock().unwrap().modify(|cfg| cfg.items.insert(profile));
+            match result {
+                Ok(_) => load_saved_items(weak.clone(), cfg.clone(), &adapters),
+                Err(err) => show_message_box(&window, "Warning", &err.to_string()),
+            }
+        }
+    });
+}
+
+fn set_update_selected(window: &Main, cfg: Arc<Mutex<ConfigFile>>, adapters: LocalAdapters) {
+    window.on_update_selected({
+        let weak = window.as_weak();
+        move |index, _dhcp_on, _texts| {
+            if index < 0 {
+                return;
+            }
+            let window = weak.unwrap();
+            let Some(name) = saved_name(&window, index) else {
+                return;
+            };
+            let result = {
+                let mut cfg = cfg.lock().unwrap();
+                // keep the identity of the saved item, only take the settings from the edit pane
+                let Some(saved) = cfg.items.find_by_name(&name).map(|item| item.nic().clone())
+                else {
+                    return;
+                };
+                let adapter = AdapterIdentity {
+                    guid: saved.guid().to_string(),
+                    name: saved.name().to_string(),
+                    index: saved.index().unwrap_or_default(),
+                    mac: None,
+                    hardware: None,
+                };
+                let (nic, template) = match utils::form_of(_dhcp_on, &_texts).to_settings(&adapter)
+                {
+                    Ok(settings) => settings,
+                    Err(err) => {
+                        show_edit_error(&window, &_texts, &err);
+                        return;
+                    }
+                };
+                cfg.modify(|cfg| {
+                    cfg.items
+                        .position(&name)
+                        .is_some_and(|i| cfg.items.update_at(i as i32, nic, template))
+                })
+            };
+            match result {
+                Ok(true) => {
+                    load_saved_items(weak.clone(), cfg.clone(), &adapters);
+                    if let Some(row) = saved_row(&window, &name) {
+                        window.invoke_select_saved(row as i32);
+                    }
+                }
+                Ok(false) => show_message_box(
+                    &window,
+                    "Warning",
+                    "The selected item is unchanged, or the same setting is already saved.",
+                ),
+                Err(err) => show_message_box(&window, "Warning", &err.to_string()),
+            }
+        }
+    });
+}
+
+/// the name of the saved item at row `index` of the list, only the items of the chosen group
+/// are shown, and changes to the config file should find the item by the name
+fn saved_name(window: &Main, index: i32) -> Option<String> {
+    let item = window
+        .global::<NetInterfaceStatus>()
+        .get_saved_settings()
+        .row_data(usize::try_from(index).ok()?)?;
+    Some(item.profile.to_string())
+}
+
+/// the row of the saved item `name` in the list, if it's shown
+fn saved_row(window: &Main, name: &str) -> Option<usize> {
+    window
+        .global::<NetInterfaceStatus>()
+        .get_saved_settings()
+        .iter()
+        .position(|item| item.profile == name)
+}
+
+/// the group chosen to show in the saved list
+fn group_filter(window: &Main) -> GroupFilter {
+    let status = window.global::<NetInterfaceStatus>();
+    match status.get_saved_group_index() {
+        0 => GroupFilter::All,
+        1 => GroupFilter::Ungrouped,
+        index => status
+            .get_group_choices()
+            .row_data(index as usize)
+            .map(|group| GroupFilter::Group(group.to_string()))
+            .unwrap_or_default(),
+    }
+}
+
+/// ask the values of the variables in `template`, `f` gets the expanded texts.
+/// The values given last time are filled in advance
+fn ask_variables(
+    window: &Main,
+    pending: &PendingAction,
+    template: Template,
+    f: impl FnOnce(&Main, Vec<SharedString>) + 'static,
+) {
+    let names = match template.variables() {
+        Ok(names) => names,
+        Err(err) => {
+            show_message_box(window, "Warning", &err.to_string());
+            return;
+        }
+    };
+    let status = window.global::<NetInterfaceStatus>();
+    let last = status
+        .get_template_variables()
+        .iter()
+        .map(|item| (item.name, item.value))
+        .collect::<HashMap<_, _>>();
+    let variables = names
+        .into_iter()
+        .map(|name| {
+            let name = SharedString::from(name);
+            TemplateVariable {
+                value: last.get(&name).cloned().unwrap_or_default(),
+                name,
+            }
+        })
+        .collect_vec();
+    status.set_template_variables(utils::create_model_vec(variables));
+
+    *pending.borrow_mut() = Some(Box::new(move |window: &Main| {
+        let values = window
+            .global::<NetInterfaceStatus>()
+            .get_template_variables()
+            .iter()
+            .map(|item| (item.name.to_string(), item.value.to_string()))
+            .collect::<HashMap<_, _>>();
+        match template.expand(&values) {
+            Ok(texts) => f(
+                window,
+                texts.into_iter().map(SharedString::from).collect_vec(),
+            ),
+            Err(err) => show_message_box(window, "Warning", &err.to_string()),
+        }
+    }));
+    window.invoke_ask_variables("Template".into());
+}
+
+/// `Load selected` with a template asks the values and fills the expanded addresses,
+/// the template is kept in the edit pane if it's cancelled
+fn set_load_selected(window: &Main, pending: PendingAction) {
+    window.on_load_selected({
+        let weak = window.as_weak();
+        move |item| {
+            let Some(template) = utils::form_of(false, &item.template).template() else {
+                return;
+            };
+            ask_variables(&weak.unwrap(), &pending, template, |window, texts| {
+                let form = ProfileForm::from_texts(false, &texts);
+                match form.check() {
+                    Ok(_) => window.invoke_fill_edit(utils::texts_of_form(&form)),
+                    Err(err) => show_message_box(window, "Warning", &err.to_string()),
+                }
+            });
+        }
+    });
+}
+
+fn refresh_adapters(
+    window: &Main,
+    backend: &SharedBackend,
+    local_adapters: &LocalAdapters,
+    nics: &SystemNics,
+    hardware: &SharedHardware,
+) {
+    window
+        .global::<NetInterfaceStatus>()
+        .set_adapters_changed(SharedString::default());
+    hardware.borrow_mut().refresh(backend);
+    update_adapters(
+        window,
+        local_adapters,
+        nics,
+        backend.adapters(),
+        &hardware.borrow(),
+    );
+}
+
+/// read the adapters if they may have changed, and show them with what changed if they did.
+/// Returns whether they changed
+fn poll_adapters(
+    window: &Main,
+    backend: &SharedBackend,
+    local_adapters: &LocalAdapters,
+    nics: &SystemNics,
+    hardware: &SharedHardware,
+    notifier: &mut Notifier,
+) -> bool {
+    if !notifier.due() {
+        return false;
+    }
+    let adapters = backend.adapters();
+    let changes = watch::diff(&nics.borrow(), &adapters);
+    if changes.is_empty() {
+        return false;
+    }
+    let time = chrono::Local::now().format("%H:%M");
+    window
+        .global::<NetInterfaceStatus>()
+        .set_adapters_changed(format!("{} {}", time, changes).into());
+    // a new adapter may have a new card
+    hardware.borrow_mut().refresh(backend);
+    update_adapters(window, local_adapters, nics, adapters, &hardware.borrow());
+    true
+}
+
+/// show `adapters`, the selected one stays selected
+fn update_adapters(
+    window: &Main,
+    local_adapters: &LocalAdapters,
+    nics: &SystemNics,
+    adapters: Vec<Nic>,
+    hardware: &HardwareCache,
+) {
+    *local_adapters.borrow_mut() = hardware.identify(&adapters);
+
+    let choices = std::iter::once("auto".into())
+        .chain(adapters.iter().map(|item| SharedString::from(item.name())))
+        .collect_vec();
+    window
+        .global::<NetInterfaceStatus>()
+        .set_adapter_choices(utils::create_model_vec(choices));
+
+    show_adapters(window, &adapters, &local_adapters.borrow());
+    *nics.borrow_mut() = adapters;
+}
+
+/// the search text as a query
+fn search_query(window: &Main) -> Query {
+    Query::parse(&window.global::<NetInterfaceStatus>().get_search_text())
+}
+
+/// the types of adapters shown besides the physical ones, see `AdapterFilter`
+fn adapter_filter(window: &Main) -> AdapterFilter {
+    let status = window.global::<NetInterfaceStatus>();
+    AdapterFilter {
+        show_virtual: status.get_show_virtual(),
+        show_loopback: status.get_show_loopback(),
+        show_unknown: status.get_show_unknown(),
+    }
+}
+
+fn show_adapter_filter(window: &Main, filter: &AdapterFilter) {
+    let status = window.global::<NetInterfaceStatus>();
+    status.set_show_virtual(filter.show_virtual);
+    status.set_show_loopback(filter.show_loopback);
+    status.set_show_unknown(filter.show_unknown);
+}
+
+/// show the adapters matching the search and the filter of types in `Adapters`
+fn show_adapters(window: &Main, adapters: &[Nic], identities: &[AdapterIdentity]) {
+    let query = search_query(window);
+    let filter = adapter_filter(window);
+    let adapters = adapters
+        .iter()
+        .map(|item| {
+            let hardware = identities
+                .iter()
+                .find(|identity| identity.guid == item.guid())
+                .and_then(|identity| identity.hardware.as_ref());
+            (item, hardware)
+        })
+        .filter(|(item, hardware)| {
+            query.matches_nic(item) && filter.shows(MediaType::of(item.name(), *hardware))
+        })
+        .collect_vec();
+    let net_interfaces = adapters
+        .iter()
+        .map(|(item, hardware)| utils::convert(item, *hardware))
+        .collect_vec();
+    let the_model = Rc::new(VecModel::from(net_interfaces));
+    let model = slint::ModelRc::from(the_model.clone());
+    window
+        .global::<NetInterfaceStatus>()
+        .set_interface_infos(model);
+
+    let list_items = adapters
+        .iter()
+        .map(|(item, _)| slint::StandardListViewItem::from(item.name()))
+        .collect_vec();
+    let list_model = utils::create_model_vec(list_items);
+    window
+        .global::<NetInterfaceStatus>()
+        .set_interface_names(list_model);
+
+    if window.get_select_system_adapter() {
+        let selected_guid = window.get_selected_guid();
+        if let Some((index, _)) = adapters
+            .iter()
+            .find_position(|(item, _)| *item.guid() == *selected_guid.as_str())
+        {
+            window.invoke_select_system(index as i32);
+        }
+    }
+}
+
+fn load_saved_items(
+    window: slint::Weak<Main>,
+    cfg: Arc<Mutex<ConfigFile>>,
+    adapters: &LocalAdapters,
+) {
+    let window = window.unwrap();
+    let status = window.global::<NetInterfaceStatus>();
+    // the rows move with the filter, the search and the changes, the selection follows the name
+    let selected = saved_name(&window, window.invoke_saved_row());
+    let (groups, profiles, scenes) = {
+        let cfg = cfg.lock().unwrap();
+        (
+            cfg.items.groups().to_vec(),
+            cfg.items.profiles().to_vec(),
+            cfg.items.scenes().to_vec(),
+        )
+    };
+
+    // keep showing the chosen group, unless it's removed
+    let filter = match group_filter(&window) {
+        GroupFilter::Group(group) if !groups.contains(&group) => GroupFilter::All,
+        filter => filter,
+    };
+    let filter_index = match &filter {
+        GroupFilter::All => 0,
+        GroupFilter::Ungrouped => 1,
+        GroupFilter::Group(group) => groups
+            .iter()
+            .position(|item| item == group)
+            .map_or(0, |i| i + 2),
+    };
+    let choices = ["all", "ungrouped"]
+        .into_iter()
+        .map(SharedString::from)
+        .chain(
+            groups
+                .iter()
+                .map(|group| SharedString::from(group.as_str())),
+        )
+        .collect_vec();
+    status.set_group_choices(utils::create_model_vec(choices));
+    status.set_saved_group_index(filter_index as i32);
+
+    let query = search_query(&window);
+    let shown = profiles
+        .iter()
+        .filter(|item| filter.matches(item) && query.matches_profile(item))
+        .collect_vec();
+    let net_interfaces = shown
+        .iter()
+        .map(|item| NetInterfaceItem {
+            profile: item.name().into(),
+            template: item
+                .template()
+                .map(|template| {
+                    utils::create_model_vec(template.texts().map(SharedString::from).to_vec())
+                })
+                .unwrap_or_default(),
+            ..utils::convert(item.nic(), item.hardware())
+        })
+        .collect_vec();
+    let the_model = Rc::new(VecModel::from(net_interfaces));
+    let model = slint::ModelRc::from(the_model.clone());
+    status.set_saved_settings(model);
+
+    let adapters = adapters.borrow();
+    let list_items = shown
+        .iter()
+        .map(|item| {
+            let orphaned = match item.binding().resolve(item.nic().guid(), &adapters) {
+                Some(_) => "",
+                None => " (not found)",
+            };
+            let template = match item.template() {
+                Some(_) => " (template)",
+                None => "",
+            };
+            slint::StandardListViewItem::from(
+                &format!("{}{}{}", item.name(), template, orphaned)[..],
+            )
+        })
+        .collect_vec();
+    let list_model = utils::create_model_vec(list_items);
+    status.set_saved_names(list_model);
+    let row = selected
+        .and_then(|name| saved_row(&window, &name))
+        .map_or(-1, |row| row as i32);
+    window.invoke_keep_saved_row(row);
+
+    let export_items = profiles
+        .iter()
+        .map(|item| ExportItem {
+            name: item.name().into(),
+            checked: false,
+        })
+        .collect_vec();
+    status.set_export_items(utils::create_model_vec(export_items.clone()));
+
+    let scene_names = scenes
+        .iter()
+        .map(|item| slint::StandardListViewItem::from(item.name.as_str()))
+        .collect_vec();
+    status.set_scene_names(utils::create_model_vec(scene_names));
+    status.set_scene_profiles(utils::create_model_vec(export_items));
+}
+
+fn load_history_items(window: &Main, history: &SharedHistory) {
+    let history = history.borrow();
+    let entries = history.entries().iter().rev().collect_vec();
+    let items = entries
+        .iter()
+        .map(|entry| {
+            let result = match entry.error {
+                Some(_) => "failed",
+                None => "ok",
+            };
+            let text = format!(
+                "{}  {}  {}  {}",
+                entry.time,
+                entry.adapter,
+                match entry.undo {
+                    true => "undo",
+                    false => entry.profile.as_deref().unwrap_or("-"),
+                },
+                result
+            );
+            slint::StandardListViewItem::from(&text[..])
+        })
+        .collect_vec();
+    let details = entries
+        .iter()
+        .map(|entry| {
+            let previous = match &entry.previous {
+                Some(previous) => history::summary(previous),
+                None => "unknown".to_string(),
+            };
+            let mut text = format!(
+                "before: {}\nafter: {}",
+                previous,
+                history::summary(&entry.applied)
+            );
+            if let Some(error) = &entry.error {
+                text.push_str(&format!("\nerror: {}", error));
+            }
+            SharedString::from(text)
+        })
+        .collect_vec();
+
+    let status = window.global::<NetInterfaceStatus>();
+    status.set_history_items(utils::create_model_vec(items));
+    status.set_history_details(utils::create_model_vec(details));
+}
+
+/// show `err`, and point at the wrong line in the edit pane if it's about the addresses
+fn show_edit_error(window: &Main, texts: &ModelRc<SharedString>, err: &cfg_ip::Error) {
+    if let cfg_ip::Error::Validation(err) = err {
+        let text = texts.row_data(err.field.index()).unwrap_or_default();
+        let (start, end) = validate::line_offsets(&text, err.line);
+        window.invoke_mark_error(
+            err.field.index() as i32,
+            start as i32,
+            end as i32,
+            err.message.as_str().into(),
+        );
+    }
+    show_message_box(window, "Warning", &err.to_string());
+}
+
+/// the warning to acknowledge before applying addresses that clash with other adapters,
+/// `None` if there are no conflicts
+fn overlap_warning(conflicts: &[overlap::Conflict]) -> Option<String> {
+    if conflicts.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "These addresses clash with other adapters, the routing may break:\n{}\n\nApply anyway?",
+        conflicts.iter().join("\n")
+    ))
+}
+
+fn show_message_box(window: &Main, title: &str, text: &str) {
+    window.invoke_show_message_box(title.into(), text.into());
+}
+
+fn show_confirm_box(
+    window: &Main,
+    pending: &PendingAction,
+    title: &str,
+    text: &str,
+    action: impl FnOnce(&Main) + 'static,
+) {
+    *pending.borrow_mut() = Some(Box::new(action));
+    window.invoke_show_confirm_box(title.into(), text.into());
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod ui_tests;
@@ -0,0 +1,470 @@
+//! Drive `Main` headless with the slint testing backend, a fake adapter source and a backend
+//! that records the applies instead of running `netsh`.
+
+use super::*;
+use crate::test_support::{nic, FakeBackend, TempDir};
+use cfg_ip::config;
+
+struct Fixture {
+    window: Main,
+    backend: Rc<FakeBackend>,
+    dir: TempDir,
+    _watcher: slint::Timer,
+}
+
+impl Fixture {
+    /// a window with `Ethernet`, static `192.168.1.10/24`, and `Wi-Fi` on DHCP in `10.0.0.0/8`
+    fn new(name: &str) -> Fixture {
+        i_slint_backend_testing::init_no_event_loop();
+        let dir = TempDir::new(&format!("ui-{}", name));
+        let backend = Rc::new(FakeBackend::new(vec![
+            nic("Ethernet", "{eth}")
+                .ip("192.168.1.10/24")
+                .gateway("192.168.1.1")
+                .dns("8.8.8.8")
+                .build(),
+            nic("Wi-Fi", "{wifi}").dhcp().ip("10.0.0.5/8").build(),
+        ]));
+        let window = Main::new().unwrap();
+        let watcher = setup(
+            &window,
+            backend.clone(),
+            &dir.join(config::CONFIG_FILE_NAME),
+        );
+        Fixture {
+            window,
+            backend,
+            dir,
+            _watcher: watcher,
+        }
+    }
+
+    fn status(&self) -> NetInterfaceStatus<'_> {
+        self.window.global::<NetInterfaceStatus>()
+    }
+
+    fn fill_edit(&self, texts: [&str; 4]) {
+        let texts = texts.into_iter().map(SharedString::from).collect_vec();
+        self.window.invoke_fill_edit(utils::create_model_vec(texts));
+    }
+
+    fn edit_texts(&self) -> Vec<String> {
+        self.window
+            .invoke_edit_texts()
+            .iter()
+            .map(|text| text.to_string())
+            .collect()
+    }
+
+    fn saved_names(&self) -> Vec<String> {
+        self.status()
+            .get_saved_names()
+            .iter()
+            .map(|item| item.text.to_string())
+            .collect()
+    }
+
+    fn applied(&self) -> Vec<Nic> {
+        self.backend.applied.borrow().clone()
+    }
+
+    /// the title and the text of the message box, `None` if it's hidden
+    fn message(&self) -> Option<(String, String)> {
+        self.window.get_message_visible().then(|| {
+            (
+                self.window.get_message_title().to_string(),
+                self.window.get_message_text().to_string(),
+            )
+        })
+    }
+}
+
+/// `Main` needs more stack than a test thread has in debug builds
+fn run(test: impl FnOnce() + Send + 'static) {
+    std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(test)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn loads_the_adapters() {
+    run(|| {
+        let app = Fixture::new("loads");
+        let names = app
+            .status()
+            .get_interface_infos()
+            .iter()
+            .map(|item| item.name.to_string())
+            .collect_vec();
+        assert_eq!(names, ["Ethernet", "Wi-Fi"]);
+        let choices = app.status().get_adapter_choices().iter().collect_vec();
+        assert_eq!(choices, ["auto", "Ethernet", "Wi-Fi"]);
+        assert!(app.saved_names().is_empty());
+        assert_eq!(app.message(), None);
+    });
+}
+
+#[test]
+fn load_an_adapter_into_the_edit_pane() {
+    run(|| {
+        let app = Fixture::new("load-adapter");
+        app.window.invoke_select_system(0);
+        app.window.invoke_load();
+        assert_eq!(
+            app.edit_texts(),
+            ["192.168.1.10", "255.255.255.0", "192.168.1.1", "8.8.8.8"]
+        );
+        assert!(!app.window.get_edit_dhcp_on());
+
+        app.window.invoke_select_system(1);
+        app.window.invoke_load();
+        assert!(app.window.get_edit_dhcp_on());
+    });
+}
+
+#[test]
+fn save_then_load_a_saved_item() {
+    run(|| {
+        let app = Fixture::new("save");
+        app.window.invoke_select_system(0);
+        app.window.invoke_load();
+        app.window.set_edit_profile_name("Office".into());
+        app.fill_edit(["192.168.3.55", "255.255.255.0", "192.168.3.1", ""]);
+        app.window.invoke_save();
+        assert_eq!(app.message(), None);
+        assert_eq!(app.saved_names(), ["Office"]);
+        let saved = std::fs::read_to_string(app.dir.join(config::CONFIG_FILE_NAME)).unwrap();
+        assert!(saved.contains("192.168.3.55"));
+
+        app.fill_edit(["", "", "", ""]);
+        app.window.invoke_select_saved(0);
+        app.window.invoke_load();
+        assert_eq!(
+            app.edit_texts(),
+            ["192.168.3.55", "255.255.255.0", "192.168.3.1", ""]
+        );
+        assert_eq!(app.window.get_edit_profile_name(), "Office");
+        assert!(app.applied().is_empty());
+    });
+}
+
+#[test]
+fn update_rewrites_the_selected_profile() {
+    run(|| {
+        let app = Fixture::new("update");
+        let texts = |texts: [&str; 4]| {
+            utils::create_model_vec(texts.into_iter().map(SharedString::from).collect_vec())
+        };
+        app.window.invoke_select_system(0);
+        app.window.invoke_load();
+        for (name, ip) in [("Office", "192.168.3.55"), ("Lab", "10.1.0.5")] {
+            app.window.set_edit_profile_name(name.into());
+            app.fill_edit([ip, "255.255.255.0", "", ""]);
+            app.window.invoke_save();
+        }
+        app.window.invoke_select_saved(0);
+
+        let office = ["192.168.3.66", "255.255.255.0", "192.168.3.1", ""];
+        app.window.invoke_update_selected(0, false, texts(office));
+        assert_eq!(app.message(), None);
+        assert_eq!(app.saved_names(), ["Office", "Lab"]);
+        assert_eq!(app.window.invoke_saved_row(), 0);
+        let path = app.dir.join(config::CONFIG_FILE_NAME);
+        let (saved, _) = ConfigFile::load(&path).unwrap();
+        let nic = saved.items.find_by_name("Office").unwrap().nic();
+        assert_eq!(
+            history::summary(nic),
+            "static 192.168.3.66/255.255.255.0 gw 192.168.3.1"
+        );
+
+        // the settings of `Lab` can't be saved twice
+        app.window
+            .invoke_update_selected(0, false, texts(["10.1.0.5", "255.255.255.0", "", ""]));
+        assert_eq!(app.message().unwrap().0, "Warning");
+        let (saved, _) = ConfigFile::load(&path).unwrap();
+        let nic = saved.items.find_by_name("Office").unwrap().nic();
+        assert_eq!(nic.address()[0].ip.to_string(), "192.168.3.66");
+        assert!(app.applied().is_empty());
+    });
+}
+
+#[test]
+fn saved_selection_follows_the_name_when_the_list_changes() {
+    run(|| {
+        let app = Fixture::new("selection");
+        app.window.invoke_select_system(0);
+        app.window.invoke_load();
+        for (name, ip) in [("Office", "192.168.3.55"), ("Lab", "10.1.0.5")] {
+            app.window.set_edit_profile_name(name.into());
+            app.fill_edit([ip, "255.255.255.0", "", ""]);
+            app.window.invoke_save();
+        }
+        assert_eq!(app.saved_names(), ["Office", "Lab"]);
+        app.window.invoke_select_saved(1);
+
+        // `Lab` moves to the first row
+        app.status().set_search_text("Lab".into());
+        app.window.invoke_search_changed();
+        assert_eq!(app.saved_names(), ["Lab"]);
+        assert_eq!(app.window.invoke_saved_row(), 0);
+
+        // `Lab` is hidden, nothing is selected anymore
+        app.status().set_search_text("Office".into());
+        app.window.invoke_search_changed();
+        assert_eq!(app.window.invoke_saved_row(), -1);
+
+        app.status().set_search_text("".into());
+        app.window.invoke_search_changed();
+        app.window.invoke_select_saved(1);
+        app.window
+            .invoke_remove_selected(app.window.invoke_saved_row());
+        assert_eq!(app.saved_names(), ["Office"]);
+        assert_eq!(app.window.invoke_saved_row(), -1);
+    });
+}
+
+#[test]
+fn load_a_template_asks_the_variables() {
+    run(|| {
+        let app = Fixture::new("template");
+        app.window.invoke_select_system(0);
+        app.window.invoke_load();
+        app.window.set_edit_profile_name("Site".into());
+        app.fill_edit(["10.{site}.0.5", "255.255.0.0", "10.{site}.0.1", ""]);
+        app.window.invoke_save();
+        assert_eq!(app.saved_names(), ["Site (template)"]);
+
+        app.window.invoke_select_saved(0);
+        app.window.invoke_load();
+        let variables = app.status().get_template_variables();
+        assert_eq!(variables.row_count(), 1);
+        let mut site = variables.row_data(0).unwrap();
+        assert_eq!(site.name, "site");
+        site.value = "20".into();
+        variables.set_row_data(0, site);
+        app.window.invoke_variables_accepted();
+        assert_eq!(
+            app.edit_texts(),
+            ["10.20.0.5", "255.255.0.0", "10.20.0.1", ""]
+        );
+    });
+}
+
+#[test]
+fn apply_the_edit_pane() {
+    run(|| {
+        let app = Fixture::new("apply");
+        app.window.invoke_select_system(0);
+        app.window.invoke_load();
+        app.fill_edit(["192.168.1.20", "255.255.255.0", "192.168.1.1", "1.1.1.1"]);
+        app.window.invoke_apply();
+        assert_eq!(app.message(), None);
+
+        let applied = app.applied();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].guid(), "{eth}");
+        assert_eq!(applied[0].address()[0].ip.to_string(), "192.168.1.20");
+        assert_eq!(app.status().get_history_items().row_count(), 1);
+    });
+}
+
+#[test]
+fn apply_a_wrong_address_shows_a_warning() {
+    run(|| {
+        let app = Fixture::new("apply-wrong");
+        app.window.invoke_select_system(0);
+        app.window.invoke_load();
+        app.fill_edit(["192.168.1.300", "255.255.255.0", "", ""]);
+        app.window.invoke_apply();
+        let (title, _) = app.message().unwrap();
+        assert_eq!(title, "Warning");
+        assert!(!app.window.get_message_confirm());
+        assert!(app.applied().is_empty());
+        assert_eq!(app.status().get_history_items().row_count(), 0);
+    });
+}
+
+#[test]
+fn apply_an_overlapping_subnet_asks_first() {
+    run(|| {
+        let app = Fixture::new("apply-overlap");
+        app.window.invoke_select_system(0);
+        app.window.invoke_load();
+        app.fill_edit(["10.1.2.3", "255.255.0.0", "", ""]);
+        app.window.invoke_apply();
+        let (title, text) = app.message().unwrap();
+        assert_eq!(title, "Overlap");
+        assert!(text.contains("Wi-Fi"));
+        assert!(app.window.get_message_confirm());
+        assert!(app.applied().is_empty());
+
+        app.window.invoke_confirm_accepted();
+        assert_eq!(app.applied().len(), 1);
+    });
+}
+
+#[test]
+fn apply_failure_shows_the_output() {
+    run(|| {
+        let app = Fixture::new("apply-fail");
+        app.backend.fail("The object already exists.", &[]);
+        app.window.invoke_select_system(0);
+        app.window.invoke_load();
+        app.fill_edit(["192.168.1.20", "255.255.255.0", "", ""]);
+        app.window.invoke_apply();
+        let (title, text) = app.message().unwrap();
+        assert_eq!(title, "Warning");
+        assert!(text.contains("The object already exists."));
+        // the failed apply is in the history too
+        assert_eq!(app.status().get_history_items().row_count(), 1);
+    });
+}
+
+#[test]
+fn changed_adapters_are_shown_and_the_selection_kept() {
+    run(|| {
+        let app = Fixture::new("watch");
+        app.window.invoke_select_system(1);
+        let backend: SharedBackend = app.backend.clone();
+        let local_adapters = LocalAdapters::default();
+        let nics = SystemNics::new(RefCell::new(backend.adapters()));
+        let hardware = SharedHardware::default();
+        let mut notifier = Notifier::polling();
+        let mut poll = || {
+            poll_adapters(
+                &app.window,
+                &backend,
+                &local_adapters,
+                &nics,
+                &hardware,
+                &mut notifier,
+            )
+        };
+        assert!(!poll());
+        assert_eq!(app.status().get_adapters_changed(), "");
+
+        let ethernet = app.backend.adapters()[0].clone();
+        *app.backend.adapters.borrow_mut() = vec![
+            nic("USB Ethernet", "{usb}").dhcp().build(),
+            ethernet,
+            nic("Wi-Fi", "{wifi}").ip("10.0.0.9/8").build(),
+        ];
+        assert!(poll());
+        let names = app
+            .status()
+            .get_interface_infos()
+            .iter()
+            .map(|item| item.name.to_string())
+            .collect_vec();
+        assert_eq!(names, ["USB Ethernet", "Ethernet", "Wi-Fi"]);
+        let changed = app.status().get_adapters_changed();
+        assert!(
+            changed.ends_with(" USB Ethernet added; Wi-Fi changed"),
+            "{}",
+            changed
+        );
+
+        // Wi-Fi is still selected, with its new address
+        app.window.invoke_load();
+        assert_eq!(app.edit_texts(), ["10.0.0.9", "255.0.0.0", "", ""]);
+
+        app.window.invoke_refresh_adapters();
+        assert_eq!(app.status().get_adapters_changed(), "");
+    });
+}
+
+#[test]
+fn cards_are_queried_off_the_ui_thread() {
+    run(|| {
+        let app = Fixture::new("cards");
+        let card = Hardware {
+            mac: "00-1A-2B-3C-4D-5E".to_string(),
+            description: "Intel(R) Ethernet Connection I219-V".to_string(),
+            media_type: Some(MediaType::Wired),
+            link_speed: Some(1_000_000_000),
+            status: Some("Up".to_string()),
+        };
+        app.backend
+            .hardware
+            .borrow_mut()
+            .insert("Ethernet".to_string(), card);
+        let backend: SharedBackend = app.backend.clone();
+        let local_adapters = LocalAdapters::default();
+        let nics = SystemNics::default();
+        let hardware = SharedHardware::default();
+        refresh_adapters(&app.window, &backend, &local_adapters, &nics, &hardware);
+        let mac = || app.status().get_interface_infos().row_data(0).unwrap().mac;
+        // shown at once, without the card
+        assert_eq!(mac(), "");
+
+        let started = std::time::Instant::now();
+        while !hardware.borrow_mut().poll() {
+            assert!(started.elapsed() < Duration::from_secs(5), "no cards");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let current = nics.borrow().clone();
+        update_adapters(
+            &app.window,
+            &local_adapters,
+            &nics,
+            current,
+            &hardware.borrow(),
+        );
+        assert_eq!(mac(), "00-1A-2B-3C-4D-5E");
+        assert_eq!(
+            local_adapters.borrow()[0].mac.as_deref(),
+            Some("00-1A-2B-3C-4D-5E")
+        );
+        // nothing new until it's queried again
+        assert!(!hardware.borrow_mut().poll());
+    });
+}
+
+#[test]
+fn virtual_adapters_are_hidden_until_shown() {
+    run(|| {
+        let app = Fixture::new("filter");
+        let names = || {
+            app.status()
+                .get_interface_infos()
+                .iter()
+                .map(|item| item.name.to_string())
+                .collect_vec()
+        };
+        app.backend.adapters.borrow_mut().extend([
+            nic("vEthernet (WSL)", "{wsl}").dhcp().build(),
+            nic("Loopback Pseudo-Interface 1", "{lo}").build(),
+            nic("Network 5", "{net5}").build(),
+        ]);
+        app.window.invoke_refresh_adapters();
+        // the unknown type is shown from the start
+        assert!(app.status().get_show_unknown());
+        assert_eq!(names(), ["Ethernet", "Wi-Fi", "Network 5"]);
+        // they can still be applied to
+        assert_eq!(app.status().get_adapter_choices().row_count(), 6);
+
+        app.status().set_show_virtual(true);
+        app.status().set_show_unknown(false);
+        app.window.invoke_adapter_filter_changed();
+        assert_eq!(names(), ["Ethernet", "Wi-Fi", "vEthernet (WSL)"]);
+
+        // the choice is kept for the next start
+        let path = app.dir.join(config::CONFIG_FILE_NAME);
+        let (saved, _) = ConfigFile::load(&path).unwrap();
+        assert!(saved.adapter_filter.show_virtual);
+        assert!(!saved.adapter_filter.show_loopback);
+        assert!(!saved.adapter_filter.show_unknown);
+        let next = Main::new().unwrap();
+        let _watcher = setup(&next, app.backend.clone(), &path);
+        assert!(next.global::<NetInterfaceStatus>().get_show_virtual());
+        assert_eq!(
+            next.global::<NetInterfaceStatus>()
+                .get_interface_infos()
+                .row_count(),
+            3
+        );
+    });
+}
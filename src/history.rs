@@ -0,0 +1,392 @@
+//! The log of the settings applied to the adapters.
+//!
+//! Each apply is a JSON line of `history.jsonl`, next to the config file, with the settings of
+//! the adapter before it. Restoring them undoes the apply. Lines that can't be read are skipped,
+//! and only the last `MAX_ENTRIES` are kept. The file is shared by the instances of the app, it's
+//! locked like the config file while an entry is recorded.
+
+use crate::backend::Backend;
+use crate::config;
+use crate::{Error, Result};
+use itertools::Itertools;
+use net_adapters::adapter::Nic;
+use serde_derive::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+pub const HISTORY_FILE_NAME: &str = "history.jsonl";
+pub const MAX_ENTRIES: usize = 200;
+
+/// An apply to an adapter
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    /// local time, like `2024-01-31 08:00:00`
+    pub time: String,
+    pub adapter: String,
+    pub guid: String,
+    /// the saved profile applied, if any
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// the settings of the adapter before, `None` if they can't be read
+    pub previous: Option<Nic>,
+    pub applied: Nic,
+    /// why it failed, `None` if it succeeded
+    #[serde(default)]
+    pub error: Option<String>,
+    /// whether it undoes an earlier apply, by `Undo` or the rollback of a scene
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub undo: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// the history file of the config file `config_path`
+    pub fn path_for(config_path: &Path) -> PathBuf {
+        config_path.with_file_name(HISTORY_FILE_NAME)
+    }
+
+    /// load the history in `path`, a missing file gives an empty history
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut entries = read_entries(&path)?;
+        if entries.len() > MAX_ENTRIES {
+            entries.drain(..entries.len() - MAX_ENTRIES);
+        }
+
+        Ok(History { path, entries })
+    }
+
+    /// an empty history for `path`, the file is untouched until `record`
+    pub fn empty(path: impl Into<PathBuf>) -> Self {
+        History {
+            path: path.into(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// the entries, the oldest first
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// the newest apply that `Undo` reverts. Each undo recorded skips one more apply, so undoing
+    /// again goes further back; a failed undo is ignored, but a failed apply may have changed
+    /// the adapter partly, so it can be undone
+    pub fn undo_entry(&self) -> Option<&HistoryEntry> {
+        let mut undone = 0;
+        for entry in self.entries.iter().rev() {
+            if entry.undo && entry.error.is_some() {
+                continue;
+            }
+            if entry.undo {
+                undone += 1;
+            } else if undone > 0 {
+                undone -= 1;
+            } else {
+                return Some(entry);
+            }
+        }
+        None
+    }
+
+    /// append `entry` to the file, it's replaced like the config file when there are too many
+    /// entries. The entries recorded by other instances meanwhile are read first
+    pub fn record(&mut self, entry: HistoryEntry) -> Result<()> {
+        let line = serde_json::to_string(&entry).map_err(Error::format(&self.path))?;
+        let _lock = config::lock(&self.path)?;
+        self.entries = read_entries(&self.path)?;
+        self.entries.push(entry);
+
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+            let mut text = String::new();
+            for entry in &self.entries {
+                text.push_str(&serde_json::to_string(entry).map_err(Error::format(&self.path))?);
+                text.push('\n');
+            }
+            config::write_atomically(&self.path, &text)?;
+        } else {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .and_then(|mut file| writeln!(file, "{}", line))
+                .map_err(Error::store(&self.path))?;
+        }
+
+        Ok(())
+    }
+
+    /// apply `nic` to its adapter by `backend` and record it, with the settings of the adapter
+    /// before. `current` is the adapters of the machine. If it's applied but can't be recorded,
+    /// the error of recording is returned
+    pub fn apply(
+        &mut self,
+        backend: &dyn Backend,
+        nic: &Nic,
+        profile: Option<&str>,
+        current: &[Nic],
+    ) -> Result<String> {
+        let (result, recorded) = self.apply_entry(backend, nic, profile, current, false);
+        let msg = result?;
+        recorded?;
+        Ok(msg)
+    }
+
+    /// like `apply`, for the settings from before an apply, to undo it
+    pub fn undo(&mut self, backend: &dyn Backend, nic: &Nic, current: &[Nic]) -> Result<String> {
+        let (result, recorded) = self.apply_entry(backend, nic, None, current, true);
+        let msg = result?;
+        recorded?;
+        Ok(msg)
+    }
+
+    /// apply `nic` and record it, or restore it by `Backend::restore` and record it as an undo
+    /// if `undo`. Returns the result of the apply and the result of recording it apart
+    pub fn apply_entry(
+        &mut self,
+        backend: &dyn Backend,
+        nic: &Nic,
+        profile: Option<&str>,
+        current: &[Nic],
+        undo: bool,
+    ) -> (Result<String>, Result<()>) {
+        let previous = current
+            .iter()
+            .find(|item| item.guid() == nic.guid())
+            .cloned();
+        let result = match undo {
+            true => backend.restore(nic),
+            false => backend.apply(nic),
+        };
+
+        let entry = HistoryEntry {
+            time: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            adapter: nic.name().to_string(),
+            guid: nic.guid().to_string(),
+            profile: profile
+                .filter(|profile| !profile.is_empty())
+                .map(str::to_string),
+            previous,
+            applied: nic.clone(),
+            error: result.as_ref().err().map(|err| err.to_string()),
+            undo,
+        };
+        let recorded = self.record(entry);
+
+        (result, recorded)
+    }
+}
+
+/// the entries in `path`, a missing file has none
+fn read_entries(path: &Path) -> Result<Vec<HistoryEntry>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(Error::store(path)(err)),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str::<HistoryEntry>(line).ok())
+        .collect())
+}
+
+/// one line description of the settings, like `static 192.168.1.2/255.255.255.0 gw 192.168.1.1`
+pub fn summary(nic: &Nic) -> String {
+    if nic.dhcp_on() {
+        return "dhcp".to_string();
+    }
+
+    let mut parts = vec!["static".to_string()];
+    parts.extend(
+        nic.address()
+            .iter()
+            .map(|item| format!("{}/{}", item.ip, item.netmask)),
+    );
+    if !nic.gateway().is_empty() {
+        parts.push(format!("gw {}", nic.gateway().iter().join(",")));
+    }
+    if !nic.dns().is_empty() {
+        parts.push(format!("dns {}", nic.dns().iter().join(",")));
+    }
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{nic, FakeBackend, TempDir};
+
+    /// `Ethernet` with the address `ip/24`
+    fn ethernet(ip: &str) -> Nic {
+        nic("Ethernet", "{eth}")
+            .ip(&format!("{}/24", ip))
+            .gateway("192.168.1.1")
+            .build()
+    }
+
+    fn entry(ip: &str) -> HistoryEntry {
+        HistoryEntry {
+            time: "2024-01-31 08:00:00".to_string(),
+            adapter: "Ethernet".to_string(),
+            guid: "{eth}".to_string(),
+            profile: None,
+            previous: None,
+            applied: ethernet(ip),
+            error: None,
+            undo: false,
+        }
+    }
+
+    fn applied(history: &History) -> Vec<String> {
+        history
+            .entries()
+            .iter()
+            .map(|entry| entry.applied.address()[0].ip.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn record_then_load_skips_broken_lines() {
+        let dir = TempDir::new("history-load");
+        let mut history = History::empty(dir.join(HISTORY_FILE_NAME));
+        history.record(entry("192.168.1.10")).unwrap();
+        OpenOptions::new()
+            .append(true)
+            .open(dir.join(HISTORY_FILE_NAME))
+            .and_then(|mut file| writeln!(file, "{{broken"))
+            .unwrap();
+        history.record(entry("192.168.1.20")).unwrap();
+
+        assert_eq!(applied(&history), ["192.168.1.10", "192.168.1.20"]);
+        let history = History::load(dir.join(HISTORY_FILE_NAME)).unwrap();
+        assert_eq!(applied(&history), ["192.168.1.10", "192.168.1.20"]);
+        assert!(History::load(dir.join("missing.jsonl"))
+            .unwrap()
+            .entries()
+            .is_empty());
+    }
+
+    #[test]
+    fn record_keeps_the_last_entries() {
+        let dir = TempDir::new("history-trim");
+        let mut history = History::empty(dir.join(HISTORY_FILE_NAME));
+        for i in 0..MAX_ENTRIES + 5 {
+            history
+                .record(entry(&format!("10.0.{}.{}", i / 256, i % 256)))
+                .unwrap();
+        }
+
+        assert_eq!(history.entries().len(), MAX_ENTRIES);
+        assert_eq!(applied(&history)[0], "10.0.0.5");
+        let text = std::fs::read_to_string(dir.join(HISTORY_FILE_NAME)).unwrap();
+        assert_eq!(text.lines().count(), MAX_ENTRIES);
+        // it's rewritten by a rename, no temporary file is left
+        assert_eq!(dir.files(), [HISTORY_FILE_NAME, "history.jsonl.lock"]);
+    }
+
+    #[test]
+    fn record_keeps_the_entries_of_other_instances() {
+        let dir = TempDir::new("history-instances");
+        let mut first = History::empty(dir.join(HISTORY_FILE_NAME));
+        let mut second = History::load(dir.join(HISTORY_FILE_NAME)).unwrap();
+        first.record(entry("192.168.1.10")).unwrap();
+        second.record(entry("192.168.1.20")).unwrap();
+
+        assert_eq!(applied(&second), ["192.168.1.10", "192.168.1.20"]);
+        let history = History::load(dir.join(HISTORY_FILE_NAME)).unwrap();
+        assert_eq!(applied(&history), ["192.168.1.10", "192.168.1.20"]);
+    }
+
+    #[test]
+    fn apply_records_the_settings_before_and_the_error() {
+        let dir = TempDir::new("history-apply");
+        let mut history = History::empty(dir.join(HISTORY_FILE_NAME));
+        let backend = FakeBackend::default();
+        let current = [ethernet("192.168.1.10")];
+
+        history
+            .apply(
+                &backend,
+                &ethernet("192.168.1.20"),
+                Some("Office"),
+                &current,
+            )
+            .unwrap();
+        backend.fail("The parameter is incorrect.", &[]);
+        let err = history
+            .apply(&backend, &ethernet("192.168.1.30"), Some(""), &current)
+            .unwrap_err();
+
+        let [ok, failed] = history.entries() else {
+            panic!("two entries expected");
+        };
+        assert_eq!(ok.profile.as_deref(), Some("Office"));
+        assert_eq!(ok.previous.as_ref(), Some(&current[0]));
+        assert_eq!(ok.error, None);
+        assert_eq!(failed.profile, None);
+        assert_eq!(failed.error, Some(err.to_string()));
+    }
+
+    #[test]
+    fn undo_goes_further_back() {
+        let dir = TempDir::new("history-undo");
+        let mut history = History::empty(dir.join(HISTORY_FILE_NAME));
+        let backend = FakeBackend::default();
+        let undo_ip = |history: &History| {
+            history
+                .undo_entry()
+                .map(|entry| entry.applied.address()[0].ip.to_string())
+        };
+        let current = [ethernet("192.168.1.10")];
+        history
+            .apply(&backend, &ethernet("192.168.1.20"), None, &current)
+            .unwrap();
+        history
+            .apply(&backend, &ethernet("192.168.1.30"), None, &current)
+            .unwrap();
+        assert_eq!(undo_ip(&history).as_deref(), Some("192.168.1.30"));
+
+        history
+            .undo(&backend, &ethernet("192.168.1.20"), &current)
+            .unwrap();
+        assert_eq!(undo_ip(&history).as_deref(), Some("192.168.1.20"));
+
+        // a failed undo is tried again
+        backend.fail("The parameter is incorrect.", &[]);
+        assert!(history
+            .undo(&backend, &ethernet("192.168.1.10"), &current)
+            .is_err());
+        assert_eq!(undo_ip(&history).as_deref(), Some("192.168.1.20"));
+
+        backend.succeed();
+        history
+            .undo(&backend, &ethernet("192.168.1.10"), &current)
+            .unwrap();
+        assert_eq!(undo_ip(&history), None);
+        // the undos restore the settings, the applies don't
+        assert_eq!(backend.restored.borrow().len(), 2);
+        assert_eq!(backend.applied.borrow().len(), 4);
+        let history = History::load(dir.join(HISTORY_FILE_NAME)).unwrap();
+        assert_eq!(
+            history.entries().iter().filter(|entry| entry.undo).count(),
+            3
+        );
+    }
+
+    #[test]
+    fn summary_of_settings() {
+        assert_eq!(
+            summary(&ethernet("192.168.1.10")),
+            "static 192.168.1.10/255.255.255.0 gw 192.168.1.1"
+        );
+        let dhcp = nic("Wi-Fi", "{wifi}").index(2).dhcp().build();
+        assert_eq!(summary(&dhcp), "dhcp");
+    }
+}
@@ -0,0 +1,318 @@
+//! Machine-readable JSON of the adapters and the apply reports, for other tools.
+//!
+//! Every document has a `format` and a `version`. The version is increased when a field is
+//! removed or changes its meaning, new fields may be added without it:
+//!
+//! ```json
+//! {
+//!   "format": "cfg-ip-adapters",
+//!   "version": 1,
+//!   "adapters": [
+//!     {
+//!       "name": "Ethernet",
+//!       "guid": "{6B29FC40-CA47-1067-B31D-00DD010662DA}",
+//!       "index": 7,
+//!       "mac": "00-1A-2B-3C-4D-5E",
+//!       "description": "Intel(R) Ethernet Connection I219-V",
+//!       "status": "up",
+//!       "type": "wired",
+//!       "link_speed": 1000000000,
+//!       "operational_status": "Up",
+//!       "dhcp": false,
+//!       "dhcp_server": null,
+//!       "address": [{ "ip": "192.168.3.55", "netmask": "255.255.255.0", "prefix": 24 }],
+//!       "gateway": ["192.168.3.1"],
+//!       "dns": ["192.168.1.1"]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! An apply report, `cfg-ip-apply`, has `dry_run`, `ok`, the `steps` applied with their
+//! `adapter`, `profile`, `settings` and `error`, the `rollback` of the adapters restored after
+//! a failed step, and the `warnings`. The saved profiles are in the format of
+//! `crate::transfer`, so they can be imported.
+
+use crate::binding::Hardware;
+use crate::cidr;
+use crate::media::MediaType;
+use net_adapters::adapter::Nic;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+pub const ADAPTERS_FORMAT: &str = "cfg-ip-adapters";
+pub const APPLY_FORMAT: &str = "cfg-ip-apply";
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressRecord {
+    pub ip: IpAddr,
+    pub netmask: IpAddr,
+    /// `None` if the netmask is not contiguous
+    pub prefix: Option<u8>,
+}
+
+/// The addresses of an adapter or of an apply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    pub dhcp: bool,
+    pub address: Vec<AddressRecord>,
+    pub gateway: Vec<IpAddr>,
+    pub dns: Vec<IpAddr>,
+}
+
+impl From<&Nic> for Settings {
+    fn from(nic: &Nic) -> Self {
+        Settings {
+            dhcp: nic.dhcp_on(),
+            address: nic
+                .address()
+                .iter()
+                .map(|item| AddressRecord {
+                    ip: item.ip,
+                    netmask: item.netmask,
+                    prefix: cidr::netmask_to_prefix(&item.netmask),
+                })
+                .collect(),
+            gateway: nic.gateway().to_vec(),
+            dns: nic.dns().to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdapterRecord {
+    pub name: String,
+    pub guid: String,
+    pub index: Option<u32>,
+    pub mac: Option<String>,
+    pub description: Option<String>,
+    pub status: Status,
+    #[serde(rename = "type")]
+    pub media_type: MediaType,
+    /// bits per second
+    pub link_speed: Option<u64>,
+    /// like `Up`, `Disconnected` or `Disabled`, as the system reports it
+    pub operational_status: Option<String>,
+    pub dhcp_server: Option<IpAddr>,
+    #[serde(flatten)]
+    pub settings: Settings,
+}
+
+impl AdapterRecord {
+    /// `hardware` is the card of the adapter, if it's known
+    pub fn new(nic: &Nic, hardware: Option<&Hardware>) -> Self {
+        AdapterRecord {
+            name: nic.name().to_string(),
+            guid: nic.guid().to_string(),
+            index: nic.index(),
+            mac: hardware.map(|item| item.mac.clone()),
+            description: hardware.map(|item| item.description.clone()),
+            media_type: MediaType::of(nic.name(), hardware),
+            link_speed: hardware.and_then(|item| item.link_speed),
+            operational_status: hardware.and_then(|item| item.status.clone()),
+            status: match nic.is_up() {
+                true => Status::Up,
+                false => Status::Down,
+            },
+            dhcp_server: nic.dhcp_server().copied(),
+            settings: Settings::from(nic),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdapterList {
+    pub format: String,
+    pub version: u32,
+    pub adapters: Vec<AdapterRecord>,
+}
+
+impl AdapterList {
+    /// `hardware` is keyed by adapter name, see `Backend::hardware`
+    pub fn new(nics: &[Nic], hardware: &HashMap<String, Hardware>) -> Self {
+        AdapterList {
+            format: ADAPTERS_FORMAT.to_string(),
+            version: VERSION,
+            adapters: nics
+                .iter()
+                .map(|nic| AdapterRecord::new(nic, hardware.get(nic.name())))
+                .collect(),
+        }
+    }
+}
+
+/// An apply to an adapter, or a restore in a rollback
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApplyStep {
+    pub adapter: String,
+    pub guid: Option<String>,
+    /// the saved profile applied, if any
+    pub profile: Option<String>,
+    /// `None` if they are unknown
+    pub settings: Option<Settings>,
+    /// why it failed, `None` if it succeeded
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApplyReport {
+    pub format: String,
+    pub version: u32,
+    /// nothing is applied, the steps are what would be applied
+    pub dry_run: bool,
+    /// every step succeeded
+    pub ok: bool,
+    pub steps: Vec<ApplyStep>,
+    #[serde(default)]
+    pub rollback: Vec<ApplyStep>,
+    /// like the subnets overlapping other adapters
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+impl ApplyReport {
+    pub fn new(dry_run: bool) -> Self {
+        ApplyReport {
+            format: APPLY_FORMAT.to_string(),
+            version: VERSION,
+            dry_run,
+            ok: true,
+            steps: Vec::new(),
+            rollback: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// add the apply of `nic`, with its error if it failed
+    pub fn push(&mut self, nic: &Nic, profile: Option<&str>, error: Option<String>) {
+        self.ok &= error.is_none();
+        self.steps.push(ApplyStep {
+            adapter: nic.name().to_string(),
+            guid: Some(nic.guid().to_string()),
+            profile: profile.map(str::to_string),
+            settings: Some(Settings::from(nic)),
+            error,
+        });
+    }
+}
+
+/// the pretty JSON of a document
+pub fn to_string(document: &impl serde::Serialize) -> crate::Result<String> {
+    serde_json::to_string_pretty(document).map_err(|err| crate::Error::Encoding(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use serde_json::{json, Value};
+
+    fn nic() -> Nic {
+        test_support::nic("Ethernet", "{eth}")
+            .index(7)
+            .ip("192.168.3.55/24")
+            .gateway("192.168.3.1")
+            .dns("8.8.8.8")
+            .build()
+    }
+
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn adapter_fields() {
+        let hardware = Hardware {
+            mac: "00-1A-2B-3C-4D-5E".to_string(),
+            description: "Intel(R) Ethernet Connection I219-V".to_string(),
+            media_type: Some(MediaType::Wired),
+            link_speed: Some(1_000_000_000),
+            status: Some("Up".to_string()),
+        };
+        let hardware = HashMap::from([("Ethernet".to_string(), hardware)]);
+        let list = serde_json::to_value(AdapterList::new(&[nic()], &hardware)).unwrap();
+        assert_eq!(keys(&list), ["adapters", "format", "version"]);
+        assert_eq!(list["format"], ADAPTERS_FORMAT);
+        assert_eq!(list["version"], VERSION);
+
+        // the settings are flattened into the adapter
+        let adapter = &list["adapters"][0];
+        assert_eq!(
+            keys(adapter),
+            [
+                "address",
+                "description",
+                "dhcp",
+                "dhcp_server",
+                "dns",
+                "gateway",
+                "guid",
+                "index",
+                "link_speed",
+                "mac",
+                "name",
+                "operational_status",
+                "status",
+                "type"
+            ]
+        );
+        assert_eq!(adapter["type"], "wired");
+        assert_eq!(adapter["status"], "down");
+        assert_eq!(adapter["link_speed"], 1_000_000_000u64);
+        assert_eq!(
+            adapter["address"],
+            json!([{ "ip": "192.168.3.55", "netmask": "255.255.255.0", "prefix": 24 }])
+        );
+        assert_eq!(adapter["gateway"], json!(["192.168.3.1"]));
+
+        // unknown hardware is null
+        let list = serde_json::to_value(AdapterList::new(&[nic()], &HashMap::new())).unwrap();
+        assert_eq!(list["adapters"][0]["mac"], Value::Null);
+    }
+
+    #[test]
+    fn apply_report_fields() {
+        let mut report = ApplyReport::new(true);
+        report.push(&nic(), Some("Office"), None);
+        report.push(&nic(), None, Some("failed".to_string()));
+        let value = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            keys(&value),
+            ["dry_run", "format", "ok", "rollback", "steps", "version", "warnings"]
+        );
+        assert_eq!(value["format"], APPLY_FORMAT);
+        assert_eq!(value["dry_run"], true);
+        assert_eq!(value["ok"], false);
+
+        let step = &value["steps"][0];
+        assert_eq!(
+            keys(step),
+            ["adapter", "error", "guid", "profile", "settings"]
+        );
+        assert_eq!(step["profile"], "Office");
+        assert_eq!(
+            keys(&step["settings"]),
+            ["address", "dhcp", "dns", "gateway"]
+        );
+        assert_eq!(value["steps"][1]["error"], "failed");
+
+        let back: ApplyReport = serde_json::from_value(value).unwrap();
+        assert_eq!(back, report);
+    }
+}
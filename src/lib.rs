@@ -1,4 +1,54 @@
+//! Read the network adapters of the machine, keep their settings as profiles and apply them.
+//!
+//! ```no_run
+//! use cfg_ip::{binding, Backend, ConfigFile, System};
+//!
+//! # fn main() -> cfg_ip::Result<()> {
+//! let (cfg, _) = ConfigFile::load("saved_items.yml")?;
+//! let adapters = binding::identify(&System.adapters(), &System.hardware()());
+//! for profile in cfg.items.profiles() {
+//!     let binding = profile.binding();
+//!     if let Some((adapter, _)) = binding.resolve(profile.nic().guid(), &adapters) {
+//!         System.apply(&profile.nic_for(adapter)?)?;
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The window is built with the `gui` feature, which is on by default. Without it the crate
+//! doesn't depend on Slint.
+
+pub mod backend;
+pub mod binding;
+pub mod cidr;
+pub mod cli;
+pub mod config;
+pub mod error;
+pub mod form;
+pub mod history;
+pub mod json;
+pub mod media;
+pub mod overlap;
+pub mod scene;
+pub mod search;
 pub mod set_ip;
 pub mod store;
+pub mod subnet;
+pub mod template;
+#[cfg(test)]
+mod test_support;
+pub mod transfer;
+#[cfg(feature = "gui")]
 pub mod ui;
+#[cfg(feature = "gui")]
 pub mod utils;
+pub mod validate;
+pub mod watch;
+
+pub use backend::{Backend, System};
+pub use config::{Config, ConfigFile};
+pub use error::{Error, Result};
+pub use form::ProfileForm;
+pub use history::History;
+pub use store::{IpConfigList, Profile, Scene};
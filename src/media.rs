@@ -0,0 +1,358 @@
+//! The media type of an adapter, guessed from its name and the description of its card.
+//!
+//! Windows names the virtual adapters of Hyper-V, WSL, VPNs and so on like real ones,
+//! `vEthernet (WSL)` or `Ethernet 3`, so the description is checked before the name.
+//! The media type reported by the system is preferred when it's known, see `reported`.
+//!
+//! The `Adapters` list hides the virtual and loopback adapters unless `AdapterFilter`
+//! turns them on, those of unknown type are shown so that no real card goes missing.
+
+use crate::binding::Hardware;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    Wired,
+    Wireless,
+    Virtual,
+    Loopback,
+    Unknown,
+}
+
+/// words of the virtual adapters, lower case
+const VIRTUAL: &[&str] = &[
+    "virtual",
+    "hyper-v",
+    "vethernet",
+    "wsl",
+    "docker",
+    "vmware",
+    "virtualbox",
+    "tap-windows",
+    "wintun",
+    "wireguard",
+    "vpn",
+    "tunnel",
+    "miniport",
+    "teredo",
+    "isatap",
+];
+const WIRELESS: &[&str] = &["wi-fi", "wifi", "wireless", "wlan", "802.11", "无线"];
+const WIRED: &[&str] = &["ethernet", "gbe", "gigabit", "以太网", "本地连接"];
+
+impl MediaType {
+    /// guess the media type of the adapter `name`, `description` may be empty if unknown
+    pub fn guess(name: &str, description: &str) -> MediaType {
+        let name = name.to_lowercase();
+        let description = description.to_lowercase();
+        let any = |words: &[&str]| {
+            words
+                .iter()
+                .any(|word| description.contains(word) || name.contains(word))
+        };
+        if any(&["loopback"]) {
+            MediaType::Loopback
+        } else if any(VIRTUAL) {
+            MediaType::Virtual
+        } else if any(WIRELESS) {
+            MediaType::Wireless
+        } else if any(WIRED) {
+            MediaType::Wired
+        } else {
+            MediaType::Unknown
+        }
+    }
+
+    /// the media type of the adapter `name`, `hardware` is its card if it's known
+    pub fn of(name: &str, hardware: Option<&Hardware>) -> MediaType {
+        match hardware {
+            Some(hardware) => hardware.media_type(name),
+            None => MediaType::guess(name, ""),
+        }
+    }
+
+    /// the media type from the properties of `Get-NetAdapter`, `None` to guess it from the names.
+    /// `medium` is the `NdisPhysicalMedium`
+    pub fn reported(
+        is_virtual: bool,
+        hardware_interface: bool,
+        medium: Option<u32>,
+    ) -> Option<MediaType> {
+        if is_virtual || !hardware_interface {
+            return Some(MediaType::Virtual);
+        }
+        match medium? {
+            // 802.3
+            14 => Some(MediaType::Wired),
+            // wireless LAN, wireless WAN, native 802.11
+            1 | 8 | 9 => Some(MediaType::Wireless),
+            _ => None,
+        }
+    }
+
+    pub fn is_physical(&self) -> bool {
+        matches!(self, MediaType::Wired | MediaType::Wireless)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MediaType::Wired => "wired",
+            MediaType::Wireless => "wireless",
+            MediaType::Virtual => "virtual",
+            MediaType::Loopback => "loopback",
+            MediaType::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Which adapters are shown besides the physical ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdapterFilter {
+    #[serde(default)]
+    pub show_virtual: bool,
+    #[serde(default)]
+    pub show_loopback: bool,
+    /// the adapters whose type can't be told, shown unless turned off
+    #[serde(default = "show_unknown")]
+    pub show_unknown: bool,
+}
+
+fn show_unknown() -> bool {
+    true
+}
+
+impl Default for AdapterFilter {
+    fn default() -> Self {
+        AdapterFilter {
+            show_virtual: false,
+            show_loopback: false,
+            show_unknown: show_unknown(),
+        }
+    }
+}
+
+impl AdapterFilter {
+    pub fn shows(&self, media_type: MediaType) -> bool {
+        match media_type {
+            MediaType::Wired | MediaType::Wireless => true,
+            MediaType::Virtual => self.show_virtual,
+            MediaType::Loopback => self.show_loopback,
+            MediaType::Unknown => self.show_unknown,
+        }
+    }
+}
+
+/// `1 Gbps`, `100 Mbps`, `866.7 Mbps` from bits per second
+pub fn format_link_speed(bits: u64) -> String {
+    let units = [
+        (1_000_000_000, "Gbps"),
+        (1_000_000, "Mbps"),
+        (1_000, "Kbps"),
+    ];
+    let Some((size, unit)) = units.into_iter().find(|(size, _)| bits >= *size) else {
+        return format!("{} bps", bits);
+    };
+    let value = format!("{:.1}", bits as f64 / size as f64);
+    format!("{} {}", value.trim_end_matches(".0"), unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hardware(description: &str, media_type: Option<MediaType>) -> Hardware {
+        Hardware {
+            mac: "00-15-5D-01-02-03".to_string(),
+            description: description.to_string(),
+            media_type,
+            link_speed: None,
+            status: None,
+        }
+    }
+
+    #[test]
+    fn guess_from_the_names() {
+        // name, description, as listed by `getmac /v` on a dev machine
+        let samples = [
+            (
+                "Ethernet",
+                "Intel(R) Ethernet Connection (7) I219-V",
+                MediaType::Wired,
+            ),
+            (
+                "以太网 2",
+                "Realtek USB GbE Family Controller",
+                MediaType::Wired,
+            ),
+            (
+                "Wi-Fi",
+                "Intel(R) Wi-Fi 6 AX201 160MHz",
+                MediaType::Wireless,
+            ),
+            (
+                "WLAN",
+                "Realtek RTL8822CE 802.11ac PCIe Adapter",
+                MediaType::Wireless,
+            ),
+            (
+                "vEthernet (WSL)",
+                "Hyper-V Virtual Ethernet Adapter",
+                MediaType::Virtual,
+            ),
+            (
+                "vEthernet (Default Switch)",
+                "Hyper-V Virtual Ethernet Adapter #2",
+                MediaType::Virtual,
+            ),
+            ("Ethernet 3", "TAP-Windows Adapter V9", MediaType::Virtual),
+            (
+                "VirtualBox Host-Only Network",
+                "VirtualBox Host-Only Ethernet Adapter",
+                MediaType::Virtual,
+            ),
+            (
+                "VMware Network Adapter VMnet8",
+                "VMware Virtual Ethernet Adapter for VMnet8",
+                MediaType::Virtual,
+            ),
+            (
+                "Docker",
+                "Hyper-V Virtual Ethernet Adapter #3",
+                MediaType::Virtual,
+            ),
+            (
+                "OpenVPN Wintun",
+                "Wintun Userspace Tunnel",
+                MediaType::Virtual,
+            ),
+            ("wg0", "WireGuard Tunnel", MediaType::Virtual),
+            (
+                "Local Area Connection* 2",
+                "WAN Miniport (IP)",
+                MediaType::Virtual,
+            ),
+            (
+                "Loopback Pseudo-Interface 1",
+                "Software Loopback Interface 1",
+                MediaType::Loopback,
+            ),
+            (
+                "Npcap Loopback Adapter",
+                "Npcap Loopback Adapter",
+                MediaType::Loopback,
+            ),
+            (
+                "Bluetooth Network Connection",
+                "Bluetooth Device (Personal Area Network)",
+                MediaType::Unknown,
+            ),
+        ];
+        for (name, description, expected) in samples {
+            assert_eq!(MediaType::guess(name, description), expected, "{}", name);
+        }
+        // without the description only the name tells
+        assert_eq!(MediaType::guess("Ethernet 3", ""), MediaType::Wired);
+        assert_eq!(MediaType::guess("vEthernet (WSL)", ""), MediaType::Virtual);
+    }
+
+    #[test]
+    fn reported_by_the_system() {
+        assert_eq!(
+            MediaType::reported(false, true, Some(14)),
+            Some(MediaType::Wired)
+        );
+        assert_eq!(
+            MediaType::reported(false, true, Some(9)),
+            Some(MediaType::Wireless)
+        );
+        assert_eq!(
+            MediaType::reported(true, true, Some(14)),
+            Some(MediaType::Virtual)
+        );
+        assert_eq!(
+            MediaType::reported(false, false, Some(0)),
+            Some(MediaType::Virtual)
+        );
+        assert_eq!(MediaType::reported(false, true, Some(10)), None);
+        assert_eq!(MediaType::reported(false, true, None), None);
+    }
+
+    #[test]
+    fn reported_type_wins_over_the_names() {
+        // a USB dongle renamed by the user, and a virtual switch named like a card
+        let dongle = hardware(
+            "ASIX AX88179 USB 3.0 to Gigabit Ethernet Adapter",
+            Some(MediaType::Wired),
+        );
+        assert_eq!(MediaType::of("Lab VPN", Some(&dongle)), MediaType::Wired);
+        let switch = hardware(
+            "Mellanox ConnectX-4 Lx Ethernet Adapter",
+            Some(MediaType::Virtual),
+        );
+        assert_eq!(
+            MediaType::of("Ethernet 2", Some(&switch)),
+            MediaType::Virtual
+        );
+
+        let unreported = hardware("Hyper-V Virtual Ethernet Adapter", None);
+        assert_eq!(
+            MediaType::of("Ethernet 2", Some(&unreported)),
+            MediaType::Virtual
+        );
+        assert_eq!(MediaType::of("Ethernet 2", None), MediaType::Wired);
+    }
+
+    #[test]
+    fn virtual_and_loopback_hidden_by_default() {
+        let filter = AdapterFilter::default();
+        let shown = [
+            MediaType::Wired,
+            MediaType::Wireless,
+            MediaType::Virtual,
+            MediaType::Loopback,
+            MediaType::Unknown,
+        ]
+        .into_iter()
+        .filter(|item| filter.shows(*item))
+        .collect::<Vec<_>>();
+        assert_eq!(
+            shown,
+            [MediaType::Wired, MediaType::Wireless, MediaType::Unknown]
+        );
+
+        let filter = AdapterFilter {
+            show_virtual: true,
+            ..AdapterFilter::default()
+        };
+        assert!(filter.shows(MediaType::Virtual));
+        assert!(!filter.shows(MediaType::Loopback));
+        assert!(filter.shows(MediaType::Unknown));
+
+        // a saved filter from before `show_unknown` keeps showing them
+        let filter: AdapterFilter = serde_yaml::from_str("show_virtual: true").unwrap();
+        assert!(filter.show_virtual);
+        assert!(filter.shows(MediaType::Unknown));
+        let filter = AdapterFilter {
+            show_unknown: false,
+            ..AdapterFilter::default()
+        };
+        assert!(!filter.shows(MediaType::Unknown));
+    }
+
+    #[test]
+    fn link_speed() {
+        assert_eq!(format_link_speed(1_000_000_000), "1 Gbps");
+        assert_eq!(format_link_speed(2_500_000_000), "2.5 Gbps");
+        assert_eq!(format_link_speed(866_700_000), "866.7 Mbps");
+        assert_eq!(format_link_speed(100_000_000), "100 Mbps");
+        assert_eq!(format_link_speed(512), "512 bps");
+    }
+}
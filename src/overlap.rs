@@ -0,0 +1,146 @@
+//! Conflicts of the addresses to apply with the other adapters of the machine.
+//!
+//! A static subnet overlapping the subnet of another adapter, like the DHCP subnet of the
+//! Wi-Fi, breaks the routing in confusing ways, and the same address on two adapters too.
+
+use crate::cidr;
+use crate::subnet::Subnet;
+use net_adapters::adapter::{Address, Nic};
+use std::fmt;
+
+/// An address to apply that clashes with an address of another adapter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub adapter: String,
+    pub address: Subnet,
+    pub other: String,
+    pub other_address: Subnet,
+}
+
+impl Conflict {
+    /// the same address, not only overlapping subnets
+    pub fn is_duplicate(&self) -> bool {
+        self.address.address() == self.other_address.address()
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_duplicate() {
+            return write!(
+                f,
+                "`{}` {}: the same address is on `{}`",
+                self.adapter,
+                self.address.address(),
+                self.other
+            );
+        }
+        write!(
+            f,
+            "`{}` {}/{} overlaps {}/{} of `{}`",
+            self.adapter,
+            self.address.address(),
+            self.address.prefix(),
+            self.other_address.address(),
+            self.other_address.prefix(),
+            self.other
+        )
+    }
+}
+
+/// the conflicts of applying `nics` together, `current` is the adapters of the machine.
+/// Each static nic is checked against the other adapters as they will be after the apply,
+/// the addresses of an adapter turning to DHCP are unknown so they are skipped
+pub fn find(nics: &[Nic], current: &[Nic]) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    for (i, nic) in nics.iter().enumerate() {
+        if nic.dhcp_on() {
+            continue;
+        }
+        let untouched = current
+            .iter()
+            .filter(|item| !nics.iter().any(|nic| nic.guid() == item.guid()));
+        // the pairs of `nics` are checked once
+        let applied = nics[i + 1..].iter().filter(|item| !item.dhcp_on());
+        for other in untouched.chain(applied) {
+            if other.guid() == nic.guid() {
+                continue;
+            }
+            for address in nic.address().iter().filter_map(subnet_of) {
+                for other_address in other.address().iter().filter_map(subnet_of) {
+                    if address.overlaps(&other_address) {
+                        conflicts.push(Conflict {
+                            adapter: nic.name().to_string(),
+                            address,
+                            other: other.name().to_string(),
+                            other_address,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    conflicts
+}
+
+/// `None` if the netmask is not contiguous
+fn subnet_of(address: &Address) -> Option<Subnet> {
+    Subnet::new(address.ip, cidr::netmask_to_prefix(&address.netmask)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_support::nic;
+
+    #[test]
+    fn overlap_with_another_adapter() {
+        let wifi = nic("Wi-Fi", "{wifi}").dhcp().ip("192.168.0.20/16").build();
+        let dongle = nic("USB", "{usb}").ip("10.0.0.2/8").build();
+        let apply = nic("USB", "{usb}").ip("192.168.3.55/24").build();
+        let conflicts = find(&[apply], &[wifi, dongle]);
+        assert_eq!(conflicts.len(), 1);
+        assert!(!conflicts[0].is_duplicate());
+        assert_eq!(
+            conflicts[0].to_string(),
+            "`USB` 192.168.3.55/24 overlaps 192.168.0.20/16 of `Wi-Fi`"
+        );
+    }
+
+    #[test]
+    fn duplicate_address() {
+        let wifi = nic("Wi-Fi", "{wifi}").ip("192.168.3.55/24").build();
+        let apply = nic("USB", "{usb}").ip("192.168.3.55/24").build();
+        let conflicts = find(&[apply], &[wifi]);
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].is_duplicate());
+    }
+
+    #[test]
+    fn no_conflict_with_itself_or_for_dhcp() {
+        let usb = nic("USB", "{usb}").ip("192.168.3.10/24").build();
+        let apply = nic("USB", "{usb}").ip("192.168.3.55/24").build();
+        assert!(find(&[apply], std::slice::from_ref(&usb)).is_empty());
+
+        let dhcp = nic("Wi-Fi", "{wifi}").dhcp().ip("192.168.3.20/24").build();
+        assert!(find(&[dhcp], &[usb]).is_empty());
+    }
+
+    #[test]
+    fn adapters_applied_together() {
+        let wifi = nic("Wi-Fi", "{wifi}").ip("192.168.3.20/24").build();
+        let usb = nic("USB", "{usb}").ip("10.0.0.2/8").build();
+        // the Wi-Fi moves away, so only the two new settings can clash
+        let new_wifi = nic("Wi-Fi", "{wifi}").ip("172.16.0.2/16").build();
+        let new_usb = nic("USB", "{usb}").ip("192.168.3.55/24").build();
+        let current = [wifi, usb];
+        assert!(find(&[new_wifi.clone(), new_usb.clone()], &current).is_empty());
+
+        let clash = nic("USB", "{usb}").ip("172.16.9.9/24").build();
+        let conflicts = find(&[new_wifi, clash], &current);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].adapter, "Wi-Fi");
+        assert_eq!(conflicts[0].other, "USB");
+    }
+}
@@ -0,0 +1,308 @@
+//! Apply the profiles of a scene to several adapters as one change.
+//!
+//! Every profile is matched to its adapter before anything is applied. If a step fails,
+//! the adapters already changed, and the failed one, are restored to the settings before.
+//! A history that can't be recorded is reported, but it doesn't fail the steps.
+
+use crate::backend::Backend;
+use crate::binding::AdapterIdentity;
+use crate::history::History;
+use crate::store::{IpConfigList, Scene};
+use crate::{Error, Result};
+use net_adapters::adapter::Nic;
+
+/// The result of applying a profile of the scene
+#[derive(Debug, Clone)]
+pub struct StepReport {
+    pub profile: String,
+    pub adapter: String,
+    /// why it failed, `None` if it succeeded
+    pub error: Option<String>,
+}
+
+/// The result of restoring an adapter after a failed step
+#[derive(Debug, Clone)]
+pub struct RollbackReport {
+    pub adapter: String,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SceneReport {
+    pub steps: Vec<StepReport>,
+    /// empty if every step succeeded
+    pub rollback: Vec<RollbackReport>,
+    /// why the history couldn't be recorded, the first time it fails
+    pub history_error: Option<String>,
+}
+
+impl SceneReport {
+    pub fn is_ok(&self) -> bool {
+        self.steps.iter().all(|step| step.error.is_none())
+    }
+
+    fn keep_history_error(&mut self, recorded: Result<()>) {
+        if let Err(err) = recorded {
+            self.history_error.get_or_insert(err.to_string());
+        }
+    }
+}
+
+/// the settings to apply for each profile of `scene`, in order.
+/// Fails if a profile is missing or a template, if an adapter can't be found,
+/// or if two profiles are for the same adapter
+pub fn plan(
+    scene: &Scene,
+    items: &IpConfigList,
+    adapters: &[AdapterIdentity],
+) -> Result<Vec<(String, Nic)>> {
+    let mut steps: Vec<(String, Nic)> = Vec::new();
+    for name in &scene.profiles {
+        let profile = items
+            .find_by_name(name)
+            .ok_or_else(|| Error::Settings(format!("the profile `{}` is not saved", name)))?;
+        if profile.template().is_some() {
+            return Err(Error::Settings(format!(
+                "the profile `{}` is a template, it can't be in a scene",
+                name
+            )));
+        }
+        let (adapter, _) = profile
+            .binding()
+            .resolve(profile.nic().guid(), adapters)
+            .ok_or_else(|| Error::Settings(format!("no adapter matches the profile `{}`", name)))?;
+        if let Some((other, _)) = steps.iter().find(|(_, nic)| nic.guid() == adapter.guid) {
+            return Err(Error::Settings(format!(
+                "the profiles `{}` and `{}` are both for `{}`",
+                other, name, adapter.name
+            )));
+        }
+        steps.push((name.clone(), profile.nic_for(adapter)?));
+    }
+
+    Ok(steps)
+}
+
+/// apply `scene` step by step, and roll back every adapter changed if a step fails.
+/// `current` is the adapters of the machine, their settings are restored in the rollback.
+/// Every apply by `backend`, including the rollback, is recorded in `history`
+pub fn apply(
+    backend: &dyn Backend,
+    scene: &Scene,
+    items: &IpConfigList,
+    adapters: &[AdapterIdentity],
+    current: &[Nic],
+    history: &mut History,
+) -> Result<SceneReport> {
+    let steps = plan(scene, items, adapters)?;
+
+    let mut report = SceneReport::default();
+    for (profile, nic) in &steps {
+        let (result, recorded) = history.apply_entry(backend, nic, Some(profile), current, false);
+        report.keep_history_error(recorded);
+        let failed = result.is_err();
+        report.steps.push(StepReport {
+            profile: profile.clone(),
+            adapter: nic.name().to_string(),
+            error: result.err().map(|err| err.to_string()),
+        });
+        if failed {
+            break;
+        }
+    }
+    if report.is_ok() {
+        return Ok(report);
+    }
+
+    // the failed step may have changed its adapter partly, so it's restored too
+    for (_, nic) in steps[..report.steps.len()].iter().rev() {
+        let error = match current.iter().find(|item| item.guid() == nic.guid()) {
+            Some(previous) => {
+                let (result, recorded) =
+                    history.apply_entry(backend, previous, None, current, true);
+                report.keep_history_error(recorded);
+                result.err().map(|err| err.to_string())
+            }
+            None => Some("the settings before are unknown".to_string()),
+        };
+        report.rollback.push(RollbackReport {
+            adapter: nic.name().to_string(),
+            error,
+        });
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::HISTORY_FILE_NAME;
+    use crate::store::Profile;
+    use crate::test_support::{nic, FakeBackend, TempDir};
+
+    const ADAPTERS: [(&str, &str, &str); 3] = [
+        ("Ethernet", "{eth}", "192.168.1.10/24"),
+        ("Wi-Fi", "{wifi}", "10.0.0.5/24"),
+        ("USB", "{usb}", "172.16.0.2/24"),
+    ];
+
+    fn current() -> Vec<Nic> {
+        ADAPTERS
+            .iter()
+            .map(|(name, guid, ip)| nic(name, guid).ip(ip).build())
+            .collect()
+    }
+
+    fn identities() -> Vec<AdapterIdentity> {
+        ADAPTERS
+            .iter()
+            .enumerate()
+            .map(|(i, (name, guid, _))| AdapterIdentity {
+                guid: guid.to_string(),
+                name: name.to_string(),
+                index: i as u32 + 1,
+                mac: None,
+                hardware: None,
+            })
+            .collect()
+    }
+
+    /// a profile per adapter, `Office` `Cafe` `Lab`, in the scene `Morning`
+    fn saved() -> (IpConfigList, Scene) {
+        let mut items = IpConfigList::default();
+        let profiles = [
+            ("Office", "10.10.1.5/24"),
+            ("Cafe", "10.10.2.5/24"),
+            ("Lab", "10.10.3.5/24"),
+        ];
+        for ((name, ip), (adapter, guid, _)) in profiles.iter().zip(ADAPTERS) {
+            assert!(items.insert(Profile::new(name, nic(adapter, guid).ip(ip).build(), None)));
+        }
+        let scene = Scene {
+            name: "Morning".to_string(),
+            profiles: profiles.iter().map(|(name, _)| name.to_string()).collect(),
+        };
+        (items, scene)
+    }
+
+    fn run(backend: &FakeBackend, history: &mut History) -> Result<SceneReport> {
+        let (items, scene) = saved();
+        apply(backend, &scene, &items, &identities(), &current(), history)
+    }
+
+    fn rollback(report: &SceneReport) -> Vec<(&str, bool)> {
+        report
+            .rollback
+            .iter()
+            .map(|item| (item.adapter.as_str(), item.error.is_none()))
+            .collect()
+    }
+
+    #[test]
+    fn every_step_applied() {
+        let dir = TempDir::new("scene-ok");
+        let mut history = History::empty(dir.join(HISTORY_FILE_NAME));
+        let backend = FakeBackend::default();
+        let report = run(&backend, &mut history).unwrap();
+
+        assert!(report.is_ok());
+        assert!(report.rollback.is_empty());
+        assert_eq!(report.history_error, None);
+        assert_eq!(
+            backend.applied_summaries(),
+            [
+                "Ethernet static 10.10.1.5/255.255.255.0",
+                "Wi-Fi static 10.10.2.5/255.255.255.0",
+                "USB static 10.10.3.5/255.255.255.0"
+            ]
+        );
+        assert_eq!(history.entries().len(), 3);
+    }
+
+    #[test]
+    fn failed_step_rolls_back() {
+        let dir = TempDir::new("scene-rollback");
+        let mut history = History::empty(dir.join(HISTORY_FILE_NAME));
+        let backend = FakeBackend::default();
+        backend.fail("The object already exists.", &["10.10.2.5"]);
+        let report = run(&backend, &mut history).unwrap();
+
+        assert!(!report.is_ok());
+        let errors = report
+            .steps
+            .iter()
+            .map(|step| (step.profile.as_str(), step.error.is_none()))
+            .collect::<Vec<_>>();
+        assert_eq!(errors, [("Office", true), ("Cafe", false)]);
+        assert_eq!(rollback(&report), [("Wi-Fi", true), ("Ethernet", true)]);
+        assert_eq!(
+            backend.applied_summaries(),
+            [
+                "Ethernet static 10.10.1.5/255.255.255.0",
+                "Wi-Fi static 10.0.0.5/255.255.255.0",
+                "Ethernet static 192.168.1.10/255.255.255.0"
+            ]
+        );
+        let restored = backend
+            .restored
+            .borrow()
+            .iter()
+            .map(|nic| nic.name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(restored, ["Wi-Fi", "Ethernet"]);
+        // the rollback undoes the steps, nothing of the scene is left to undo
+        assert!(history.undo_entry().is_none());
+    }
+
+    #[test]
+    fn failed_rollback_is_reported() {
+        let dir = TempDir::new("scene-failed-rollback");
+        let mut history = History::empty(dir.join(HISTORY_FILE_NAME));
+        let backend = FakeBackend::default();
+        backend.fail("The object already exists.", &["10.10.3.5", "192.168.1.10"]);
+        let report = run(&backend, &mut history).unwrap();
+
+        assert_eq!(report.steps.len(), 3);
+        assert_eq!(
+            rollback(&report),
+            [("USB", true), ("Wi-Fi", true), ("Ethernet", false)]
+        );
+    }
+
+    #[test]
+    fn history_error_does_not_fail_the_steps() {
+        let dir = TempDir::new("scene-history");
+        // the history can't be written under a file
+        std::fs::write(dir.join("file"), "").unwrap();
+        let mut history = History::empty(dir.join("file").join("history.jsonl"));
+        let backend = FakeBackend::default();
+        let report = run(&backend, &mut history).unwrap();
+
+        assert!(report.is_ok());
+        assert!(report.rollback.is_empty());
+        assert!(report.history_error.is_some());
+        assert_eq!(backend.applied.borrow().len(), 3);
+    }
+
+    #[test]
+    fn plan_refuses_missing_and_shared_adapters() {
+        let (mut items, mut scene) = saved();
+        scene.profiles.push("Home".to_string());
+        let err = plan(&scene, &items, &identities()).unwrap_err();
+        assert_eq!(err.to_string(), "the profile `Home` is not saved");
+
+        scene.profiles.pop();
+        assert!(items.insert(Profile::new(
+            "Home",
+            nic("Ethernet", "{eth}").ip("10.10.4.5/24").build(),
+            None
+        )));
+        scene.profiles.push("Home".to_string());
+        let err = plan(&scene, &items, &identities()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the profiles `Office` and `Home` are both for `Ethernet`"
+        );
+    }
+}
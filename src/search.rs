@@ -0,0 +1,216 @@
+//! Filter the adapters and the saved profiles by the search box.
+//!
+//! The text is split by spaces, and every term must match. A term is
+//! - a subnet like `192.168.3.0/24`, matching the addresses, gateways and dns servers in it;
+//! - digits and dots like `192.168.3`, matching the addresses, gateways and dns servers
+//!   starting with those octets, so `192.168.3` doesn't match `192.168.30.1`;
+//! - any other text, matching a part of the adapter name, the profile name,
+//!   an address or a template, case insensitive.
+
+use crate::cidr;
+use crate::store::Profile;
+use crate::subnet::Subnet;
+use net_adapters::adapter::Nic;
+use std::net::IpAddr;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Text(String),
+    IpPrefix(String),
+    Subnet(Subnet),
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Query {
+        let terms = text.split_whitespace().map(parse_term).collect();
+        Query { terms }
+    }
+
+    /// an empty query matches everything
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches_nic(&self, nic: &Nic) -> bool {
+        self.matches(&[nic.name()], nic)
+    }
+
+    pub fn matches_profile(&self, profile: &Profile) -> bool {
+        let mut texts = vec![profile.name(), profile.nic().name()];
+        if let Some(template) = profile.template() {
+            texts.extend(template.texts());
+        }
+        self.matches(&texts, profile.nic())
+    }
+
+    fn matches(&self, texts: &[&str], nic: &Nic) -> bool {
+        let ips = nic
+            .address()
+            .iter()
+            .map(|item| item.ip)
+            .chain(nic.gateway().iter().copied())
+            .chain(nic.dns().iter().copied())
+            .collect::<Vec<_>>();
+
+        self.terms.iter().all(|term| match term {
+            Term::Text(text) => {
+                texts.iter().any(|item| item.to_lowercase().contains(text))
+                    || ips.iter().any(|ip| ip.to_string().contains(text))
+            }
+            Term::IpPrefix(prefix) => ips.iter().any(|ip| has_octets(ip, prefix)),
+            Term::Subnet(subnet) => ips.iter().any(|ip| subnet.contains(ip)),
+        })
+    }
+}
+
+fn parse_term(term: &str) -> Term {
+    if let Some(subnet) = cidr::parse_cidr(term).and_then(|(ip, len)| Subnet::new(ip, len)) {
+        return Term::Subnet(subnet);
+    }
+    if term.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && term.contains(|c: char| c.is_ascii_digit())
+    {
+        return Term::IpPrefix(term.trim_end_matches('.').to_string());
+    }
+    Term::Text(term.to_lowercase())
+}
+
+/// whether the leading octets of `ip` are `prefix`
+fn has_octets(ip: &IpAddr, prefix: &str) -> bool {
+    let text = ip.to_string();
+    match text.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::nic;
+
+    #[test]
+    fn empty_query_matches_everything() {
+        let query = Query::parse("   ");
+        assert!(query.is_empty());
+        assert!(query.matches_nic(
+            &nic("Ethernet", "{guid}")
+                .ip("10.0.0.2/24")
+                .gateway("10.0.0.1")
+                .dns("8.8.8.8")
+                .build()
+        ));
+    }
+
+    #[test]
+    fn ip_prefix_matches_whole_octets() {
+        let lab = nic("Ethernet", "{guid}")
+            .ip("192.168.3.55/24")
+            .gateway("192.168.3.1")
+            .dns("8.8.8.8")
+            .build();
+        let other = nic("Ethernet", "{guid}")
+            .ip("192.168.30.2/24")
+            .gateway("192.168.30.1")
+            .dns("8.8.8.8")
+            .build();
+        let query = Query::parse("192.168.3");
+        assert!(query.matches_nic(&lab));
+        assert!(!query.matches_nic(&other));
+        assert!(Query::parse("192.168.3.").matches_nic(&lab));
+        assert!(Query::parse("192.168.30").matches_nic(&other));
+    }
+
+    #[test]
+    fn ip_prefix_matches_gateway_and_dns() {
+        let item = nic("Ethernet", "{guid}")
+            .ip("10.0.0.2/24")
+            .gateway("10.0.0.1")
+            .dns("114.114.114.114")
+            .build();
+        assert!(Query::parse("114.114").matches_nic(&item));
+        assert!(Query::parse("10.0.0.1").matches_nic(&item));
+        assert!(!Query::parse("10.0.0.3").matches_nic(&item));
+    }
+
+    #[test]
+    fn subnet_matches_addresses_inside() {
+        let item = nic("Ethernet", "{guid}")
+            .ip("172.16.5.20/24")
+            .gateway("172.16.5.1")
+            .dns("8.8.8.8")
+            .build();
+        assert!(Query::parse("172.16.0.0/16").matches_nic(&item));
+        assert!(Query::parse("172.16.5.0/24").matches_nic(&item));
+        assert!(!Query::parse("172.16.6.0/24").matches_nic(&item));
+        assert!(Query::parse("0.0.0.0/0").matches_nic(&item));
+        assert!(Query::parse("172.16.5.20/32").matches_nic(&item));
+    }
+
+    #[test]
+    fn invalid_subnet_is_text() {
+        assert_eq!(
+            Query::parse("10.0.0.0/33").terms,
+            vec![Term::Text("10.0.0.0/33".to_string())]
+        );
+    }
+
+    #[test]
+    fn text_matches_names_case_insensitive() {
+        let item = nic("Wi-Fi", "{guid}")
+            .ip("10.0.0.2/24")
+            .gateway("10.0.0.1")
+            .dns("8.8.8.8")
+            .build();
+        assert!(Query::parse("wi-fi").matches_nic(&item));
+        assert!(Query::parse("FI").matches_nic(&item));
+        assert!(!Query::parse("ethernet").matches_nic(&item));
+    }
+
+    #[test]
+    fn all_terms_must_match() {
+        let item = nic("Ethernet", "{guid}")
+            .ip("192.168.3.55/24")
+            .gateway("192.168.3.1")
+            .dns("8.8.8.8")
+            .build();
+        assert!(Query::parse("ether 192.168.3").matches_nic(&item));
+        assert!(!Query::parse("ether 10.1").matches_nic(&item));
+    }
+
+    #[test]
+    fn profile_matches_its_name_and_adapter() {
+        let profile = Profile::new(
+            "Office",
+            nic("Ethernet", "{guid}")
+                .ip("192.168.3.55/24")
+                .gateway("192.168.3.1")
+                .dns("8.8.8.8")
+                .build(),
+            None,
+        );
+        assert!(Query::parse("office").matches_profile(&profile));
+        assert!(Query::parse("ethernet").matches_profile(&profile));
+        assert!(Query::parse("192.168.3.0/24").matches_profile(&profile));
+        assert!(!Query::parse("home").matches_profile(&profile));
+    }
+
+    #[test]
+    fn profile_matches_template_text() {
+        let mut profile = Profile::new("Site", nic("Ethernet", "{guid}").build(), None);
+        let texts = [
+            "10.20.{site}.50".to_string(),
+            "255.255.255.0".to_string(),
+            String::new(),
+            String::new(),
+        ];
+        profile.set_template(crate::template::Template::from_texts(&texts));
+        assert!(Query::parse("{site}").matches_profile(&profile));
+        assert!(!Query::parse("{host}").matches_profile(&profile));
+    }
+}
@@ -1,11 +1,16 @@
 use encoding::{all::GB18030, DecoderTrap, Encoding};
-use std::io::Write;
+use std::io::{self, Write};
 use std::process::{Command, Stdio};
 
-use anyhow::{anyhow, Result};
-use net_adapters::adapter::Address;
+use crate::validate::{Field, FieldError};
+use crate::{Error, Result};
+use itertools::Itertools;
+use net_adapters::adapter::{Address, Nic};
 use std::net::IpAddr;
 
+/// printed by the shell after a command that failed, with the position of the command
+const FAILED_MARKER: &str = "cfg-ip failed:";
+
 fn format_ip_address(ip: &IpAddr) -> String {
     match ip {
         IpAddr::V4(ip) => ip.to_string(),
@@ -13,32 +18,112 @@ fn format_ip_address(ip: &IpAddr) -> String {
     }
 }
 
+/// apply the settings of `nic` to the adapter named `nic.name()`, a static one needs an address
+pub fn apply(nic: &Nic) -> Result<String> {
+    if nic.dhcp_on() {
+        return set_dynamic_ip(nic.name());
+    }
+    set_static_ip(nic.name(), nic.address(), nic.gateway(), nic.dns())
+}
+
+/// put back the settings of an adapter read before an apply, to undo it. Unlike `apply`, static
+/// settings without any address are restored by removing the addresses of the adapter
+pub fn restore(nic: &Nic) -> Result<String> {
+    if !nic.dhcp_on() && nic.address().is_empty() {
+        return clear_static_ip(nic.name(), nic.dns());
+    }
+    apply(nic)
+}
+
+/// remove every address of the adapter, like the settings of an adapter that had none,
+/// and set the `dns` servers. `netsh` deletes an address by its value, so they're read first
+pub fn clear_static_ip(nic_name: &str, dns: &[IpAddr]) -> Result<String> {
+    // netsh interface ip delete address name="WLAN" addr=10.8.4.159 gateway=all
+    // netsh interface ip set dns name="WLAN" source=static address=none
+    let name_field = format!("name=\"{}\"", nic_name);
+    let addresses = net_adapters::adapter::get_adapters()
+        .into_iter()
+        .find(|item| item.name() == nic_name)
+        .map(|item| item.address().to_vec())
+        .unwrap_or_default();
+
+    let mut commands = addresses
+        .iter()
+        .map(|address| {
+            [
+                "netsh.exe",
+                "interface",
+                "ip",
+                "delete",
+                "address",
+                &name_field,
+                &format!("addr={}", format_ip_address(&address.ip)),
+                "gateway=all",
+            ]
+            .join(" ")
+        })
+        .collect_vec();
+    commands.push(
+        [
+            "netsh.exe",
+            "interface",
+            "ip",
+            "set",
+            "dns",
+            &name_field,
+            "source=static",
+            "address=none",
+        ]
+        .join(" "),
+    );
+    for dns in dns {
+        commands.push(
+            [
+                "netsh.exe",
+                "interface",
+                "ip",
+                "add",
+                "dns",
+                &name_field,
+                &format_ip_address(dns),
+            ]
+            .join(" "),
+        );
+    }
+
+    shell_batch(commands)
+}
+
+/// turn DHCP on for the address and the dns servers of the adapter `nic_name`
 pub fn set_dynamic_ip(nic_name: &str) -> Result<String> {
+    // the state is read rather than the message of netsh matched, the message is translated
+    let dhcp_on = net_adapters::adapter::get_adapters()
+        .into_iter()
+        .find(|item| item.name() == nic_name)
+        .is_some_and(|item| item.dhcp_on());
+    shell_batch(dynamic_commands(nic_name, dhcp_on))
+}
+
+/// netsh fails to set the address if DHCP is already on, so it's left out then
+fn dynamic_commands(nic_name: &str, dhcp_on: bool) -> Vec<String> {
     // netsh interface ip set address name="WLAN" source=dhcp
     // netsh interface ip set dns name="WLAN" source=dhcp
-    let cmd_set_dynamic = [
-        "netsh.exe",
-        "interface",
-        "ip",
-        "set",
-        "address",
-        &format!("name=\"{}\"", nic_name),
-        "source=dhcp",
-    ]
-    .join(" ");
-
-    let cmd_set_dns = [
-        "netsh.exe",
-        "interface",
-        "ip",
-        "set",
-        "dns",
-        &format!("name=\"{}\"", nic_name),
-        "source=dhcp",
-    ]
-    .join(" ");
-
-    shell_batch(vec![cmd_set_dynamic, cmd_set_dns])
+    ["address", "dns"]
+        .into_iter()
+        .filter(|what| !dhcp_on || *what == "dns")
+        .map(|what| {
+            [
+                "netsh.exe",
+                "interface",
+                "ip",
+                "set",
+                what,
+                &format!("name=\"{}\"", nic_name),
+                "source=dhcp",
+            ]
+            .join(" ")
+        })
+        .collect()
 }
 
 pub fn set_static_ip(
@@ -54,7 +139,9 @@ pub fn set_static_ip(
     // Netsh interface IP add dns "WLAN" 114.114.114.114
     // Netsh interface IP add dns "WLAN" 58.20.127.238
 
-    assert!(!address.is_empty());
+    if address.is_empty() {
+        return Err(FieldError::new(Field::Ip, 1, "no address to set").into());
+    }
     let name_field = format!("name=\"{}\"", nic_name);
 
     let cmd_set_static = [
@@ -130,30 +217,81 @@ pub fn set_static_ip(
     shell_batch(commands)
 }
 
+/// run `commands` in order, and return the output.
+/// The commands after a failed one are still run, and the first failure is returned
 fn shell_batch(commands: Vec<String>) -> Result<String> {
+    // `/q` keeps the commands out of the output
     let mut child = Command::new("cmd.exe")
+        .arg("/q")
         .stdin(Stdio::piped())
-        .stdout(Stdio::inherit())
-        .spawn()?;
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(Error::Spawn)?;
 
-    let mut stdin = child.stdin.take().expect("Failed to open stdin");
-    for cmd in commands {
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| Error::Spawn(io::Error::other("the input of the shell is not piped")))?;
+    for (i, cmd) in commands.iter().enumerate() {
+        let mut line = cmd.trim_end_matches('\n').to_string();
+        line.push_str(&format!("\nif errorlevel 1 echo {}{}\n", FAILED_MARKER, i));
         let s = GB18030
-            .encode(&cmd, encoding::EncoderTrap::Strict)
-            .map_err(|err| anyhow!(err.to_string()))?;
-        stdin.write_all(&s[..])?;
-        if !cmd.ends_with('\n') {
-            stdin.write_all("\n".as_bytes())?;
-        }
+            .encode(&line, encoding::EncoderTrap::Strict)
+            .map_err(|err| Error::Encoding(err.to_string()))?;
+        stdin.write_all(&s[..]).map_err(Error::Spawn)?;
     }
+    // the shell exits at the end of the input
+    drop(stdin);
 
-    stdin.write_all("echo \"You can close the window now.\"\n".as_bytes())?;
-    // stdin.write_all("exit\n".as_bytes())?;
-
-    let _output = child.wait_with_output()?;
+    let output = child.wait_with_output().map_err(Error::Spawn)?;
     let msg = GB18030
-        .decode(&_output.stdout, DecoderTrap::Strict)
-        .expect("format std output failed");
+        .decode(&output.stdout, DecoderTrap::Strict)
+        .map_err(|err| Error::Encoding(err.to_string()))?;
+
+    let failed = msg.lines().find_map(|line| {
+        let i = line.trim().strip_prefix(FAILED_MARKER)?;
+        commands.get(i.parse::<usize>().ok()?)
+    });
+    if let Some(command) = failed {
+        return Err(Error::Command {
+            command: command.clone(),
+            output: msg
+                .lines()
+                .filter(|line| !line.trim().starts_with(FAILED_MARKER))
+                .join("\n"),
+        });
+    }
 
     Ok(msg)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::nic;
+
+    #[test]
+    fn static_without_address_is_refused_before_any_command() {
+        let nic = nic("Ethernet", "{eth}").dns("8.8.8.8").build();
+        // no shell is spawned, or it would fail with `Spawn` here
+        match apply(&nic) {
+            Err(Error::Validation(err)) => assert_eq!((err.field, err.line), (Field::Ip, 1)),
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn dynamic_leaves_out_the_address_when_dhcp_is_on() {
+        assert_eq!(
+            dynamic_commands("Wi-Fi", false),
+            [
+                "netsh.exe interface ip set address name=\"Wi-Fi\" source=dhcp",
+                "netsh.exe interface ip set dns name=\"Wi-Fi\" source=dhcp",
+            ]
+        );
+        assert_eq!(
+            dynamic_commands("Wi-Fi", true),
+            ["netsh.exe interface ip set dns name=\"Wi-Fi\" source=dhcp"]
+        );
+    }
+}
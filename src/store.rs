@@ -1,17 +1,228 @@
+use crate::binding::{AdapterBinding, AdapterIdentity, Hardware};
+use crate::template::Template;
+use crate::{Error, Result};
 use itertools::Itertools;
 use net_adapters::adapter::Nic;
 use serde_derive::{Deserialize, Serialize};
-use std::collections::HashMap;
+
+/// A saved setting, and the rules to find its adapter on the current machine
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Profile {
+    #[serde(default)]
+    name: String,
+    nic: Nic,
+    binding: AdapterBinding,
+    /// the group in the saved list, `None` for ungrouped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    /// addresses with variables, `nic` has no addresses when it's set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    template: Option<Template>,
+    /// the card of the adapter it's saved from, if it was known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hardware: Option<Hardware>,
+}
+
+impl Profile {
+    pub fn new(name: &str, nic: Nic, mac: Option<String>) -> Self {
+        let binding = AdapterBinding::new(nic.name(), mac);
+        Profile::with_binding(name, nic, binding)
+    }
+
+    pub fn with_binding(name: &str, nic: Nic, binding: AdapterBinding) -> Self {
+        let name = match name.trim() {
+            "" => default_name(&nic),
+            name => name.to_string(),
+        };
+        Profile {
+            name,
+            nic,
+            binding,
+            group: None,
+            template: None,
+            hardware: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn nic(&self) -> &Nic {
+        &self.nic
+    }
+
+    pub fn binding(&self) -> &AdapterBinding {
+        &self.binding
+    }
+
+    /// the settings of the profile for `adapter`, which may differ from the adapter it's saved from
+    pub fn nic_for(&self, adapter: &AdapterIdentity) -> Result<Nic> {
+        let nic = &self.nic;
+        Nic::new(
+            &adapter.name,
+            adapter.index,
+            &adapter.guid,
+            nic.dhcp_on(),
+            Some(nic.address().to_vec()),
+            Some(nic.gateway().to_vec()),
+            Some(nic.dns().to_vec()),
+        )
+        .map_err(Error::settings)
+    }
+
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    pub fn template(&self) -> Option<&Template> {
+        self.template.as_ref()
+    }
+
+    pub fn set_template(&mut self, template: Option<Template>) {
+        self.template = template;
+    }
+
+    pub fn hardware(&self) -> Option<&Hardware> {
+        self.hardware.as_ref()
+    }
+
+    pub fn set_hardware(&mut self, hardware: Option<Hardware>) {
+        self.hardware = hardware;
+    }
+
+    /// put the profile in `group`, an empty name means ungrouped
+    pub fn set_group(&mut self, group: Option<&str>) {
+        self.group = group
+            .map(str::trim)
+            .filter(|group| !group.is_empty())
+            .map(str::to_string);
+    }
+}
+
+impl From<Nic> for Profile {
+    fn from(nic: Nic) -> Self {
+        Profile::new("", nic, None)
+    }
+}
+
+/// name for a profile saved without one, like `Ethernet - static`
+pub fn default_name(nic: &Nic) -> String {
+    let mode = match nic.dhcp_on() {
+        true => "dhcp",
+        false => "static",
+    };
+    format!("{} - {}", nic.name(), mode)
+}
+
+/// whether two profiles have equal settings for the same adapter, the adapter index is ignored
+fn same_settings(a: &Profile, b: &Profile) -> bool {
+    let (nic_a, nic_b) = (&a.nic, &b.nic);
+    nic_a.guid() == nic_b.guid()
+        && nic_a.dhcp_on() == nic_b.dhcp_on()
+        && nic_a.address() == nic_b.address()
+        && nic_a.gateway() == nic_b.gateway()
+        && nic_a.dns() == nic_b.dns()
+        && a.template == b.template
+}
+
+/// What to do with an imported profile whose name is already used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// keep both, the imported one is renamed
+    Merge,
+    /// keep the saved one
+    Skip,
+    /// overwrite the saved one
+    Replace,
+}
+
+/// Which profiles of the saved list are shown
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum GroupFilter {
+    #[default]
+    All,
+    Ungrouped,
+    Group(String),
+}
+
+impl GroupFilter {
+    pub fn matches(&self, profile: &Profile) -> bool {
+        match self {
+            GroupFilter::All => true,
+            GroupFilter::Ungrouped => profile.group.is_none(),
+            GroupFilter::Group(group) => profile.group.as_ref() == Some(group),
+        }
+    }
+}
+
+/// Profiles for several adapters, applied together
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Scene {
+    pub name: String,
+    /// names of the profiles, applied in this order
+    pub profiles: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportReport {
+    pub added: usize,
+    pub renamed: usize,
+    pub replaced: usize,
+    pub skipped: usize,
+}
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(from = "StoredList")]
 pub struct IpConfigList {
-    inner: HashMap<String, Vec<Nic>>,
+    /// user defined groups, in the order shown. A group may have no profiles
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<String>,
+    profiles: Vec<Profile>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    scenes: Vec<Scene>,
+}
+
+/// The layout on disk, the names of the profiles are checked when loading
+#[derive(Deserialize)]
+struct StoredList {
+    #[serde(default)]
+    groups: Vec<String>,
+    #[serde(default)]
+    profiles: Vec<Profile>,
+    #[serde(default)]
+    scenes: Vec<Scene>,
+}
+
+impl From<StoredList> for IpConfigList {
+    fn from(value: StoredList) -> Self {
+        let mut list = IpConfigList::default();
+        for group in value.groups {
+            list.add_group(&group);
+        }
+        for mut profile in value.profiles {
+            // names must be unique, files saved before naming profiles have no names at all
+            if profile.name.is_empty() {
+                profile.name = default_name(&profile.nic);
+            }
+            profile.name = list.unique_name(&profile.name);
+            list.add_profile_group(&profile);
+            list.profiles.push(profile);
+        }
+        for scene in value.scenes {
+            list.save_scene(scene);
+        }
+        list
+    }
 }
 
 #[allow(unused)]
 impl IpConfigList {
-    pub fn get_by_guid(&self, key: &str) -> Option<&Vec<Nic>> {
-        self.inner.get(key)
+    pub fn get_by_guid(&self, key: &str) -> Vec<&Profile> {
+        self.profiles
+            .iter()
+            .filter(|item| item.nic.guid() == key)
+            .collect_vec()
     }
 
     pub fn contains(&self, nic: &Nic) -> bool {
@@ -19,68 +230,431 @@ impl IpConfigList {
     }
 
     pub fn try_get(&self, nic: &Nic) -> Option<&Nic> {
-        if let Some(v) = self.inner.get(nic.guid()) {
-            v.iter().find(|item| *item == nic)
-        } else {
-            None
-        }
+        self.profiles
+            .iter()
+            .map(|item| &item.nic)
+            .find(|item| *item == nic)
     }
 
     pub fn try_get_mut(&mut self, nic: &Nic) -> Option<&mut Nic> {
-        if let Some(v) = self.inner.get_mut(nic.guid()) {
-            v.iter_mut().find(|item| *item == nic)
+        self.profiles
+            .iter_mut()
+            .map(|item| &mut item.nic)
+            .find(|item| *item == nic)
+    }
+
+    pub fn remove_by_guid(&mut self, key: &str) -> Vec<Profile> {
+        let (removed, kept): (Vec<Profile>, _) = std::mem::take(&mut self.profiles)
+            .into_iter()
+            .partition(|item| item.nic.guid() == key);
+        self.profiles = kept;
+        for item in &removed {
+            self.rename_in_scenes(&item.name, None);
+        }
+        removed
+    }
+
+    pub fn remove(&mut self, nic: &Nic) -> Option<Nic> {
+        let (i, _) = self
+            .profiles
+            .iter()
+            .find_position(|item| item.nic == *nic)?;
+        self.remove_at(i as i32)
+    }
+
+    /// remove the profile at `index`, and from the scenes
+    pub fn remove_at(&mut self, index: i32) -> Option<Nic> {
+        let index = usize::try_from(index).ok()?;
+        if index < self.profiles.len() {
+            let profile = self.profiles.remove(index);
+            self.rename_in_scenes(&profile.name, None);
+            Some(profile.nic)
         } else {
             None
         }
     }
 
-    pub fn remove_by_guid(&mut self, key: &str) -> Option<Vec<Nic>> {
-        self.inner.remove(key)
+    /// rename the profile `name` to `new_name`, in the scenes too.
+    /// Returns false if there is no such profile, or `new_name` is empty or already used
+    pub fn rename(&mut self, name: &str, new_name: &str) -> bool {
+        let new_name = new_name.trim();
+        if new_name.is_empty() || self.find_by_name(new_name).is_some() {
+            return false;
+        }
+        let Some(i) = self.position(name) else {
+            return false;
+        };
+        self.profiles[i].name = new_name.to_string();
+        self.rename_in_scenes(name, Some(new_name));
+        true
     }
 
-    pub fn remove(&mut self, nic: &Nic) -> Option<Nic> {
-        if let Some(v) = self.inner.get_mut(nic.guid()) {
-            if let Some((i, _)) = v.iter().enumerate().find(|(i, item)| *item == nic) {
-                let removed_val = v.remove(i);
-                if v.is_empty() {
-                    self.inner.remove(nic.guid());
-                }
-                return Some(removed_val);
+    /// rename the profile `name` in every scene, or take it out if `new_name` is `None`
+    fn rename_in_scenes(&mut self, name: &str, new_name: Option<&str>) {
+        for scene in &mut self.scenes {
+            match new_name {
+                Some(new_name) => scene
+                    .profiles
+                    .iter_mut()
+                    .filter(|item| *item == name)
+                    .for_each(|item| *item = new_name.to_string()),
+                None => scene.profiles.retain(|item| item != name),
             }
         }
-
-        None
     }
 
-    pub fn remove_at(&mut self, index: i32) -> Option<Nic> {
-        assert!(index >= 0);
-        if let Some(nic) = self.inner.values().flatten().nth(index as usize) {
-            let nic = nic.clone();
-            self.remove(&nic)
+    /// append `profile`, it's renamed if the name is already used
+    pub fn insert(&mut self, mut profile: Profile) -> bool {
+        if self.contains_profile(&profile.nic, profile.template.as_ref()) {
+            false
         } else {
-            None
+            profile.name = self.unique_name(&profile.name);
+            self.add_profile_group(&profile);
+            self.profiles.push(profile);
+            true
         }
     }
 
-    pub fn insert(&mut self, nic: Nic) -> bool {
-        let entry = self.inner.entry(nic.guid().to_string());
-        match entry {
-            std::collections::hash_map::Entry::Occupied(mut entry) => {
-                if !entry.get().iter().any(|item| nic.eq(item)) {
-                    entry.get_mut().push(nic);
-                    true
-                } else {
-                    false
-                }
+    /// whether a profile with `nic` and `template` is saved
+    fn contains_profile(&self, nic: &Nic, template: Option<&Template>) -> bool {
+        self.profiles
+            .iter()
+            .any(|item| item.nic == *nic && item.template.as_ref() == template)
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.profiles.iter().position(|item| item.name == name)
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|item| item.name == name)
+    }
+
+    /// `name`, or `name (2)`, `name (3)`... if it's already used
+    pub fn unique_name(&self, name: &str) -> String {
+        let mut unique = name.to_string();
+        let mut count = 1;
+        while self.find_by_name(&unique).is_some() {
+            count += 1;
+            unique = format!("{} ({})", name, count);
+        }
+        unique
+    }
+
+    /// add `profiles` read from a file, see `ConflictPolicy` for profiles with used names.
+    /// Profiles with the same settings as a saved one are always skipped
+    pub fn import(&mut self, profiles: Vec<Profile>, policy: ConflictPolicy) -> ImportReport {
+        let mut report = ImportReport::default();
+        for mut profile in profiles {
+            if self
+                .profiles
+                .iter()
+                .any(|item| same_settings(item, &profile))
+            {
+                report.skipped += 1;
+                continue;
             }
-            std::collections::hash_map::Entry::Vacant(v) => {
-                v.insert(vec![nic]);
-                true
+
+            let Some(i) = self
+                .profiles
+                .iter()
+                .position(|item| item.name == profile.name)
+            else {
+                self.add_profile_group(&profile);
+                self.profiles.push(profile);
+                report.added += 1;
+                continue;
+            };
+            match policy {
+                ConflictPolicy::Merge => {
+                    profile.name = self.unique_name(&profile.name);
+                    self.add_profile_group(&profile);
+                    self.profiles.push(profile);
+                    report.renamed += 1;
+                }
+                ConflictPolicy::Skip => report.skipped += 1,
+                ConflictPolicy::Replace => {
+                    self.add_profile_group(&profile);
+                    self.profiles[i] = profile;
+                    report.replaced += 1;
+                }
             }
         }
+
+        report
+    }
+
+    /// overwrite the settings of the item at `index` with `nic` and `template`,
+    /// keeping its position in the list.
+    /// `nic` must belong to the same adapter, and must not duplicate another item
+    pub fn update_at(&mut self, index: i32, nic: Nic, template: Option<Template>) -> bool {
+        let Ok(index) = usize::try_from(index) else {
+            return false;
+        };
+        match self.profiles.get(index) {
+            Some(old) if old.nic.guid() == nic.guid() => {}
+            _ => return false,
+        };
+        if self.contains_profile(&nic, template.as_ref()) {
+            return false;
+        }
+
+        let item = &mut self.profiles[index];
+        item.nic = nic;
+        item.template = template;
+        true
+    }
+
+    /// move every profile saved from the adapter `guid` to `adapter`,
+    /// returns the number of profiles changed
+    pub fn rebind(&mut self, guid: &str, adapter: &AdapterIdentity) -> Result<usize> {
+        let mut count = 0;
+        for item in self
+            .profiles
+            .iter_mut()
+            .filter(|item| item.nic.guid() == guid)
+        {
+            item.nic = item.nic_for(adapter)?;
+            item.binding = AdapterBinding::new(&adapter.name, adapter.mac.clone());
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    pub fn groups(&self) -> &[String] {
+        &self.groups
+    }
+
+    /// add an empty group, returns false if the name is empty or already used
+    pub fn add_group(&mut self, group: &str) -> bool {
+        let group = group.trim();
+        if group.is_empty() || self.groups.iter().any(|item| item == group) {
+            return false;
+        }
+        self.groups.push(group.to_string());
+        true
+    }
+
+    /// remove `group`, its profiles are kept as ungrouped
+    pub fn remove_group(&mut self, group: &str) -> bool {
+        let Some(i) = self.groups.iter().position(|item| item == group) else {
+            return false;
+        };
+        self.groups.remove(i);
+        for item in self
+            .profiles
+            .iter_mut()
+            .filter(|item| item.group.as_deref() == Some(group))
+        {
+            item.group = None;
+        }
+        true
+    }
+
+    /// move the profile `name` to `group`, which is added if it's new.
+    /// `None` or an empty name moves it out of any group
+    pub fn move_to_group(&mut self, name: &str, group: Option<&str>) -> bool {
+        let Some(i) = self.position(name) else {
+            return false;
+        };
+        self.profiles[i].set_group(group);
+        let profile = self.profiles[i].clone();
+        self.add_profile_group(&profile);
+        true
+    }
+
+    fn add_profile_group(&mut self, profile: &Profile) {
+        if let Some(group) = &profile.group {
+            self.add_group(group);
+        }
+    }
+
+    pub fn scenes(&self) -> &[Scene] {
+        &self.scenes
+    }
+
+    pub fn find_scene(&self, name: &str) -> Option<&Scene> {
+        self.scenes.iter().find(|item| item.name == name)
+    }
+
+    /// add `scene`, or replace the one with the same name.
+    /// Returns false if the name is empty
+    pub fn save_scene(&mut self, mut scene: Scene) -> bool {
+        scene.name = scene.name.trim().to_string();
+        if scene.name.is_empty() {
+            return false;
+        }
+        match self.scenes.iter_mut().find(|item| item.name == scene.name) {
+            Some(item) => *item = scene,
+            None => self.scenes.push(scene),
+        }
+        true
+    }
+
+    pub fn remove_scene(&mut self, name: &str) -> Option<Scene> {
+        let i = self.scenes.iter().position(|item| item.name == name)?;
+        Some(self.scenes.remove(i))
+    }
+
+    pub fn profiles(&self) -> &[Profile] {
+        &self.profiles
     }
 
     pub fn get_list(&self) -> Vec<Nic> {
-        self.inner.values().flatten().cloned().collect_vec()
+        self.profiles
+            .iter()
+            .map(|item| item.nic.clone())
+            .collect_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::nic;
+
+    fn profile(name: &str, guid: &str, ip: &str) -> Profile {
+        let nic = nic("Ethernet", guid).ip(&format!("{}/24", ip)).build();
+        Profile::new(name, nic, None)
+    }
+
+    fn saved() -> IpConfigList {
+        let mut list = IpConfigList::default();
+        assert!(list.insert(profile("Office", "{eth}", "192.168.3.55")));
+        assert!(list.insert(profile("Lab", "{eth}", "10.0.0.5")));
+        list
+    }
+
+    fn names(list: &IpConfigList) -> Vec<&str> {
+        list.profiles().iter().map(Profile::name).collect()
+    }
+
+    fn ip(list: &IpConfigList, name: &str) -> String {
+        list.find_by_name(name).unwrap().nic().address()[0]
+            .ip
+            .to_string()
+    }
+
+    #[test]
+    fn unique_name_suffix() {
+        let mut list = saved();
+        assert_eq!(list.unique_name("Home"), "Home");
+        assert_eq!(list.unique_name("Office"), "Office (2)");
+        assert!(list.insert(profile("Office (2)", "{eth}", "172.16.0.5")));
+        assert_eq!(list.unique_name("Office"), "Office (3)");
+    }
+
+    #[test]
+    fn update_at_keeps_the_position() {
+        let mut list = saved();
+        let ethernet = |ip: &str| nic("Ethernet", "{eth}").ip(ip).build();
+        assert!(!list.update_at(-1, ethernet("10.0.0.6/24"), None));
+        assert!(!list.update_at(2, ethernet("10.0.0.6/24"), None));
+        // another adapter, or the settings of `Office`
+        let wifi = nic("Wi-Fi", "{wifi}").ip("10.0.0.6/24").build();
+        assert!(!list.update_at(1, wifi, None));
+        assert!(!list.update_at(1, ethernet("192.168.3.55/24"), None));
+        assert_eq!(ip(&list, "Lab"), "10.0.0.5");
+
+        assert!(list.update_at(1, ethernet("10.0.0.6/24"), None));
+        assert_eq!(names(&list), ["Office", "Lab"]);
+        assert_eq!(ip(&list, "Lab"), "10.0.0.6");
+
+        // a template replaces the addresses, and the addresses replace it
+        let texts = ["10.{site}.0.5", "255.255.0.0", "", ""].map(String::from);
+        let template = Template::from_texts(&texts).unwrap();
+        let empty = nic("Ethernet", "{eth}").build();
+        assert!(list.update_at(1, empty, Some(template.clone())));
+        let lab = list.find_by_name("Lab").unwrap();
+        assert_eq!(lab.template(), Some(&template));
+        assert!(lab.nic().address().is_empty());
+        assert!(list.update_at(1, ethernet("10.0.0.7/24"), None));
+        assert_eq!(list.find_by_name("Lab").unwrap().template(), None);
+        assert_eq!(names(&list), ["Office", "Lab"]);
+    }
+
+    #[test]
+    fn import_merge_renames() {
+        let mut list = saved();
+        let report = list.import(
+            vec![
+                profile("Office", "{eth}", "192.168.4.55"),
+                profile("Home", "{eth}", "192.168.1.5"),
+            ],
+            ConflictPolicy::Merge,
+        );
+        assert_eq!(
+            report,
+            ImportReport {
+                added: 1,
+                renamed: 1,
+                ..ImportReport::default()
+            }
+        );
+        assert_eq!(names(&list), ["Office", "Lab", "Office (2)", "Home"]);
+        assert_eq!(ip(&list, "Office"), "192.168.3.55");
+        assert_eq!(ip(&list, "Office (2)"), "192.168.4.55");
+    }
+
+    #[test]
+    fn import_skip_keeps_the_saved_one() {
+        let mut list = saved();
+        let report = list.import(
+            vec![profile("Office", "{eth}", "192.168.4.55")],
+            ConflictPolicy::Skip,
+        );
+        assert_eq!(report.skipped, 1);
+        assert_eq!(names(&list), ["Office", "Lab"]);
+        assert_eq!(ip(&list, "Office"), "192.168.3.55");
+    }
+
+    #[test]
+    fn import_replace_overwrites_in_place() {
+        let mut list = saved();
+        let report = list.import(
+            vec![profile("Office", "{eth}", "192.168.4.55")],
+            ConflictPolicy::Replace,
+        );
+        assert_eq!(report.replaced, 1);
+        assert_eq!(names(&list), ["Office", "Lab"]);
+        assert_eq!(ip(&list, "Office"), "192.168.4.55");
+    }
+
+    #[test]
+    fn import_skips_the_same_settings() {
+        for policy in [
+            ConflictPolicy::Merge,
+            ConflictPolicy::Skip,
+            ConflictPolicy::Replace,
+        ] {
+            let mut list = saved();
+            // another name, but the settings of `Lab`
+            let report = list.import(vec![profile("Lab copy", "{eth}", "10.0.0.5")], policy);
+            assert_eq!(report.skipped, 1);
+            assert_eq!(names(&list), ["Office", "Lab"]);
+        }
+    }
+
+    #[test]
+    fn scenes_follow_renamed_and_removed_profiles() {
+        let mut list = saved();
+        assert!(list.insert(profile("Wi-Fi", "{wifi}", "10.1.0.5")));
+        list.save_scene(Scene {
+            name: "Morning".to_string(),
+            profiles: vec!["Office".to_string(), "Wi-Fi".to_string()],
+        });
+        let scene = |list: &IpConfigList| list.find_scene("Morning").unwrap().profiles.clone();
+
+        assert!(!list.rename("Office", "Lab"));
+        assert!(!list.rename("Home", "Cafe"));
+        assert!(list.rename("Office", " Desk "));
+        assert_eq!(names(&list), ["Desk", "Lab", "Wi-Fi"]);
+        assert_eq!(scene(&list), ["Desk", "Wi-Fi"]);
+
+        list.remove_at(list.position("Desk").unwrap() as i32);
+        assert_eq!(scene(&list), ["Wi-Fi"]);
+        list.remove_by_guid("{wifi}");
+        assert!(scene(&list).is_empty());
     }
 }
@@ -0,0 +1,279 @@
+//! The subnet of an address and a prefix, for the subnet calculator.
+//!
+//! IPv4 subnets up to /30 exclude the network and broadcast addresses from the hosts,
+//! /31 has two hosts and /32 one. IPv6 has no broadcast, every address is a host.
+
+use crate::cidr;
+use crate::{Error, Result};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subnet {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl Subnet {
+    /// `None` if the prefix is too long for the address
+    pub fn new(address: IpAddr, prefix: u8) -> Option<Subnet> {
+        cidr::prefix_to_netmask(prefix, address.is_ipv4())?;
+        Some(Subnet { address, prefix })
+    }
+
+    /// parse `192.168.3.55/24` with an empty `mask`,
+    /// or an address with a mask like `255.255.255.0`, `24` or `/24`
+    pub fn parse(address: &str, mask: &str) -> Result<Subnet> {
+        let (address, mask) = (address.trim(), mask.trim());
+        if address.contains('/') {
+            if !mask.is_empty() {
+                return Err(Error::Settings(
+                    "give either a prefix or a netmask, not both".to_string(),
+                ));
+            }
+            let (ip, prefix) = cidr::parse_cidr(address).ok_or_else(|| {
+                Error::Settings(format!("`{}` is not an address with a prefix", address))
+            })?;
+            return Ok(Subnet {
+                address: ip,
+                prefix,
+            });
+        }
+
+        let ip = address
+            .parse::<IpAddr>()
+            .map_err(|_| Error::Settings(format!("`{}` is not an address", address)))?;
+        if mask.is_empty() {
+            return Err(Error::Settings(
+                "a netmask or a prefix like 24 is needed".to_string(),
+            ));
+        }
+        let prefix = match mask.trim_start_matches('/').parse::<u8>() {
+            Ok(prefix) => prefix,
+            Err(_) => {
+                let netmask = mask.parse::<IpAddr>().map_err(|_| {
+                    Error::Settings(format!("`{}` is not a netmask or a prefix", mask))
+                })?;
+                if netmask.is_ipv4() != ip.is_ipv4() {
+                    return Err(Error::Settings(format!(
+                        "{} doesn't fit the address {}",
+                        netmask, ip
+                    )));
+                }
+                cidr::netmask_to_prefix(&netmask).ok_or_else(|| {
+                    Error::Settings(format!(
+                        "{} is not a valid netmask, the ones must be contiguous",
+                        netmask
+                    ))
+                })?
+            }
+        };
+        Subnet::new(ip, prefix)
+            .ok_or_else(|| Error::Settings(format!("/{} is too long for {}", prefix, ip)))
+    }
+
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    pub fn netmask(&self) -> IpAddr {
+        from_bits(self.mask_bits(), self.is_v4())
+    }
+
+    pub fn network(&self) -> IpAddr {
+        from_bits(to_bits(&self.address) & self.mask_bits(), self.is_v4())
+    }
+
+    /// `None` for IPv6
+    pub fn broadcast(&self) -> Option<IpAddr> {
+        self.is_v4().then(|| from_bits(self.last_bits(), true))
+    }
+
+    /// the first and the last usable host
+    pub fn hosts(&self) -> (IpAddr, IpAddr) {
+        let first = to_bits(&self.address) & self.mask_bits();
+        let last = self.last_bits();
+        match self.is_v4() && self.prefix <= 30 {
+            true => (from_bits(first + 1, true), from_bits(last - 1, true)),
+            false => (
+                from_bits(first, self.is_v4()),
+                from_bits(last, self.is_v4()),
+            ),
+        }
+    }
+
+    /// the number of usable hosts, `u128::MAX` for an IPv6 /0
+    pub fn host_count(&self) -> u128 {
+        let host_bits = self.width() - self.prefix as u32;
+        let count = 1u128.checked_shl(host_bits).unwrap_or(u128::MAX);
+        match self.is_v4() && self.prefix <= 30 {
+            true => count - 2,
+            false => count,
+        }
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        ip.is_ipv4() == self.is_v4() && to_bits(ip) & self.mask_bits() == to_bits(&self.network())
+    }
+
+    /// whether some address is in both subnets
+    pub fn overlaps(&self, other: &Subnet) -> bool {
+        self.contains(&other.network()) || other.contains(&self.network())
+    }
+
+    fn is_v4(&self) -> bool {
+        self.address.is_ipv4()
+    }
+
+    fn width(&self) -> u32 {
+        match self.is_v4() {
+            true => 32,
+            false => 128,
+        }
+    }
+
+    fn mask_bits(&self) -> u128 {
+        to_bits(&cidr::prefix_to_netmask(self.prefix, self.is_v4()).unwrap())
+    }
+
+    fn last_bits(&self) -> u128 {
+        let all = match self.is_v4() {
+            true => u32::MAX as u128,
+            false => u128::MAX,
+        };
+        to_bits(&self.address) & self.mask_bits() | (all & !self.mask_bits())
+    }
+}
+
+fn to_bits(ip: &IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u32::from(*ip) as u128,
+        IpAddr::V6(ip) => u128::from(*ip),
+    }
+}
+
+fn from_bits(bits: u128, v4: bool) -> IpAddr {
+    match v4 {
+        true => IpAddr::V4(Ipv4Addr::from(bits as u32)),
+        false => IpAddr::V6(Ipv6Addr::from(bits)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn v4_subnet() {
+        let subnet = Subnet::parse("192.168.3.55", "255.255.255.0").unwrap();
+        assert_eq!(subnet.prefix(), 24);
+        assert_eq!(subnet.netmask(), ip("255.255.255.0"));
+        assert_eq!(subnet.network(), ip("192.168.3.0"));
+        assert_eq!(subnet.broadcast(), Some(ip("192.168.3.255")));
+        assert_eq!(subnet.hosts(), (ip("192.168.3.1"), ip("192.168.3.254")));
+        assert_eq!(subnet.host_count(), 254);
+    }
+
+    #[test]
+    fn v4_subnet_not_on_octets() {
+        let subnet = Subnet::parse("10.20.37.200", "20").unwrap();
+        assert_eq!(subnet.netmask(), ip("255.255.240.0"));
+        assert_eq!(subnet.network(), ip("10.20.32.0"));
+        assert_eq!(subnet.broadcast(), Some(ip("10.20.47.255")));
+        assert_eq!(subnet.hosts(), (ip("10.20.32.1"), ip("10.20.47.254")));
+        assert_eq!(subnet.host_count(), 4094);
+    }
+
+    #[test]
+    fn v4_point_to_point_and_single_host() {
+        let subnet = Subnet::parse("10.0.0.1/31", "").unwrap();
+        assert_eq!(subnet.hosts(), (ip("10.0.0.0"), ip("10.0.0.1")));
+        assert_eq!(subnet.host_count(), 2);
+
+        let subnet = Subnet::parse("10.0.0.7", "/32").unwrap();
+        assert_eq!(subnet.network(), ip("10.0.0.7"));
+        assert_eq!(subnet.hosts(), (ip("10.0.0.7"), ip("10.0.0.7")));
+        assert_eq!(subnet.host_count(), 1);
+
+        let subnet = Subnet::parse("10.0.0.5/30", "").unwrap();
+        assert_eq!(subnet.hosts(), (ip("10.0.0.5"), ip("10.0.0.6")));
+        assert_eq!(subnet.host_count(), 2);
+    }
+
+    #[test]
+    fn v4_whole_space() {
+        let subnet = Subnet::parse("1.2.3.4", "0").unwrap();
+        assert_eq!(subnet.network(), ip("0.0.0.0"));
+        assert_eq!(subnet.broadcast(), Some(ip("255.255.255.255")));
+        assert_eq!(subnet.host_count(), (1u128 << 32) - 2);
+    }
+
+    #[test]
+    fn v6_subnet() {
+        let subnet = Subnet::parse("2001:db8::1234/64", "").unwrap();
+        assert_eq!(subnet.network(), ip("2001:db8::"));
+        assert_eq!(subnet.broadcast(), None);
+        assert_eq!(
+            subnet.hosts(),
+            (ip("2001:db8::"), ip("2001:db8::ffff:ffff:ffff:ffff"))
+        );
+        assert_eq!(subnet.host_count(), 1u128 << 64);
+        assert_eq!(Subnet::parse("::1", "0").unwrap().host_count(), u128::MAX);
+    }
+
+    #[test]
+    fn contains_gateway() {
+        let subnet = Subnet::parse("192.168.3.55/24", "").unwrap();
+        assert!(subnet.contains(&ip("192.168.3.1")));
+        assert!(!subnet.contains(&ip("192.168.4.1")));
+        assert!(!subnet.contains(&ip("::1")));
+        let subnet = Subnet::parse("172.16.5.20", "255.255.0.0").unwrap();
+        assert!(subnet.contains(&ip("172.16.200.1")));
+    }
+
+    #[test]
+    fn overlapping_subnets() {
+        let wifi = Subnet::parse("192.168.0.20/16", "").unwrap();
+        let lab = Subnet::parse("192.168.3.55/24", "").unwrap();
+        let other = Subnet::parse("192.169.3.55/24", "").unwrap();
+        assert!(wifi.overlaps(&lab));
+        assert!(lab.overlaps(&wifi));
+        assert!(!lab.overlaps(&other));
+        assert!(!wifi.overlaps(&Subnet::parse("::1/0", "").unwrap()));
+    }
+
+    #[test]
+    fn formatted_address_parses_back() {
+        for (address, netmask) in [
+            ("192.168.3.55", "255.255.255.0"),
+            ("fe80::1", "ffff:ffff::"),
+        ] {
+            let text = cidr::format_cidr(&ip(address), &ip(netmask)).unwrap();
+            let subnet = Subnet::parse(&text, "").unwrap();
+            assert_eq!(subnet.address(), ip(address));
+            assert_eq!(subnet.netmask(), ip(netmask));
+        }
+        assert_eq!(
+            cidr::format_cidr(&ip("192.168.3.55"), &ip("255.0.255.0")),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Subnet::parse("192.168.3", "24").is_err());
+        assert!(Subnet::parse("192.168.3.55", "").is_err());
+        assert!(Subnet::parse("192.168.3.55", "33").is_err());
+        assert!(Subnet::parse("192.168.3.55", "255.0.255.0").is_err());
+        assert!(Subnet::parse("192.168.3.55", "ffff::").is_err());
+        assert!(Subnet::parse("192.168.3.55/24", "24").is_err());
+        assert!(Subnet::parse("192.168.3.55/40", "").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Profiles whose addresses contain variables, like `10.20.{site}.50`.
+//!
+//! A variable is a name of letters, digits and `_` in braces. The values are asked
+//! when the template is loaded or applied, and the expanded texts are checked as usual.
+
+use crate::{Error, Result};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The texts of the edit pane with variables, one address per line
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Template {
+    #[serde(default)]
+    pub ip: String,
+    #[serde(default)]
+    pub netmask: String,
+    #[serde(default)]
+    pub gateway: String,
+    #[serde(default)]
+    pub dns: String,
+}
+
+impl Template {
+    /// the template of the texts `ip, netmask, gateway, dns`, `None` if there is no variable
+    pub fn from_texts(texts: &[String]) -> Option<Template> {
+        let [ip, netmask, gateway, dns] = texts else {
+            return None;
+        };
+        if !texts.iter().any(|text| text.contains('{')) {
+            return None;
+        }
+        Some(Template {
+            ip: ip.clone(),
+            netmask: netmask.clone(),
+            gateway: gateway.clone(),
+            dns: dns.clone(),
+        })
+    }
+
+    /// the texts in the order of the edit pane
+    pub fn texts(&self) -> [&str; 4] {
+        [&self.ip, &self.netmask, &self.gateway, &self.dns]
+    }
+
+    /// names of the variables, in the order they first appear
+    pub fn variables(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for text in self.texts() {
+            for name in parse(text)?.into_iter().filter_map(|part| match part {
+                Part::Variable(name) => Some(name),
+                Part::Text(_) => None,
+            }) {
+                if !names.iter().any(|item| item == name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /// replace the variables by `values`, every variable must have a value
+    pub fn expand(&self, values: &HashMap<String, String>) -> Result<Vec<String>> {
+        self.texts()
+            .into_iter()
+            .map(|text| {
+                parse(text)?
+                    .into_iter()
+                    .map(|part| match part {
+                        Part::Text(text) => Ok(text),
+                        Part::Variable(name) => match values.get(name).map(|value| value.trim()) {
+                            Some(value) if value.contains(['{', '}']) => Err(Error::Template(
+                                format!("the value of `{{{}}}` has braces", name),
+                            )),
+                            Some(value) if !value.is_empty() => Ok(value),
+                            _ => Err(Error::Template(format!("no value for `{{{}}}`", name))),
+                        },
+                    })
+                    .collect::<Result<String>>()
+            })
+            .collect()
+    }
+}
+
+enum Part<'a> {
+    Text(&'a str),
+    Variable(&'a str),
+}
+
+fn parse(text: &str) -> Result<Vec<Part<'_>>> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        if rest[..start].contains('}') {
+            return Err(Error::Template(format!(
+                "`}}` is not opened in `{}`",
+                text.trim()
+            )));
+        }
+        parts.push(Part::Text(&rest[..start]));
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| Error::Template(format!("`{{` is not closed in `{}`", text.trim())))?;
+        let name = &rest[start + 1..start + end];
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(Error::Template(format!(
+                "`{{{}}}` is not a valid variable",
+                name
+            )));
+        }
+        parts.push(Part::Variable(name));
+        rest = &rest[start + end + 1..];
+    }
+    if rest.contains('}') {
+        return Err(Error::Template(format!(
+            "`}}` is not opened in `{}`",
+            text.trim()
+        )));
+    }
+    parts.push(Part::Text(rest));
+
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(texts: [&str; 4]) -> Template {
+        Template::from_texts(&texts.map(String::from)).unwrap()
+    }
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn texts_without_variables_are_not_templates() {
+        let texts = ["192.168.3.55", "255.255.255.0", "", ""].map(String::from);
+        assert_eq!(Template::from_texts(&texts), None);
+        assert_eq!(Template::from_texts(&texts[..3]), None);
+    }
+
+    #[test]
+    fn variables_in_order_once() {
+        let site = template([
+            "10.20.{site}.50\n10.20.{site}.{host}",
+            "255.255.255.0",
+            "10.20.{site}.1",
+            "{dns_1}",
+        ]);
+        assert_eq!(site.variables().unwrap(), ["site", "host", "dns_1"]);
+    }
+
+    #[test]
+    fn wrong_braces_and_names() {
+        let error = |ip: &str| {
+            template([ip, "", "", "{x}"])
+                .variables()
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("10.20.{site.50"),
+            "`{` is not closed in `10.20.{site.50`"
+        );
+        assert_eq!(
+            error("10.20.site}.50"),
+            "`}` is not opened in `10.20.site}.50`"
+        );
+        assert_eq!(
+            error("10.20.{site}}.50"),
+            "`}` is not opened in `10.20.{site}}.50`"
+        );
+        assert_eq!(error("10.20.{}.50"), "`{}` is not a valid variable");
+        assert_eq!(
+            error("10.20.{the site}.50"),
+            "`{the site}` is not a valid variable"
+        );
+        assert_eq!(error("10.{{site}}.50"), "`{{site}` is not a valid variable");
+    }
+
+    #[test]
+    fn expand_every_text() {
+        let site = template(["10.20.{site}.{host}", "255.255.255.0", "10.20.{site}.1", ""]);
+        let texts = site
+            .expand(&values(&[("site", " 7 "), ("host", "50")]))
+            .unwrap();
+        assert_eq!(texts, ["10.20.7.50", "255.255.255.0", "10.20.7.1", ""]);
+    }
+
+    #[test]
+    fn expand_needs_every_value() {
+        let site = template(["10.20.{site}.{host}", "", "", ""]);
+        let error = |pairs| site.expand(&values(pairs)).unwrap_err().to_string();
+        assert_eq!(error(&[("site", "7")]), "no value for `{host}`");
+        assert_eq!(
+            error(&[("site", "7"), ("host", "  ")]),
+            "no value for `{host}`"
+        );
+        assert_eq!(
+            error(&[("site", "{host}"), ("host", "50")]),
+            "the value of `{site}` has braces"
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! The fixtures shared by the tests: a backend that records the applies instead of running
+//! `netsh`, a temporary directory and a builder of adapters.
+//!
+//! The UI tests of the binary include this file too, the binary imports the modules of the
+//! library at its root so the `crate::` paths below lead to them.
+
+use crate::backend::{Backend, HardwareQuery};
+use crate::binding::Hardware;
+use crate::{cidr, Error, Result};
+use net_adapters::adapter::{Address, Nic};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// A backend with the adapters in `adapters`, they take the settings applied like on the machine
+#[derive(Default)]
+pub struct FakeBackend {
+    pub adapters: RefCell<Vec<Nic>>,
+    /// every apply and restore that succeeded, in order
+    pub applied: RefCell<Vec<Nic>>,
+    /// the restores among them, by undo and the rollback of a scene
+    pub restored: RefCell<Vec<Nic>>,
+    /// the output of the applies that fail, `None` to succeed
+    pub fail: RefCell<Option<String>>,
+    /// if not empty, only the applies of these addresses fail
+    pub fail_only: RefCell<Vec<IpAddr>>,
+    /// the cards of the adapters, by name
+    pub hardware: RefCell<HashMap<String, Hardware>>,
+}
+
+impl FakeBackend {
+    pub fn new(adapters: Vec<Nic>) -> Self {
+        FakeBackend {
+            adapters: RefCell::new(adapters),
+            ..FakeBackend::default()
+        }
+    }
+
+    /// fail the applies with `output`, only those of `addresses` if any are given
+    pub fn fail(&self, output: &str, addresses: &[&str]) {
+        *self.fail.borrow_mut() = Some(output.to_string());
+        *self.fail_only.borrow_mut() = addresses.iter().map(|ip| ip.parse().unwrap()).collect();
+    }
+
+    pub fn succeed(&self) {
+        *self.fail.borrow_mut() = None;
+        self.fail_only.borrow_mut().clear();
+    }
+
+    /// the applies like `Ethernet static 10.0.0.5/255.255.255.0`
+    pub fn applied_summaries(&self) -> Vec<String> {
+        self.applied
+            .borrow()
+            .iter()
+            .map(|nic| format!("{} {}", nic.name(), crate::history::summary(nic)))
+            .collect()
+    }
+
+    fn set(&self, nic: &Nic) -> Result<String> {
+        let fail_only = self.fail_only.borrow();
+        let fails = fail_only.is_empty()
+            || nic
+                .address()
+                .iter()
+                .any(|address| fail_only.contains(&address.ip));
+        if let Some(output) = self.fail.borrow().clone().filter(|_| fails) {
+            return Err(Error::Command {
+                command: format!("netsh.exe interface ip set address name=\"{}\"", nic.name()),
+                output,
+            });
+        }
+        self.applied.borrow_mut().push(nic.clone());
+        for item in self.adapters.borrow_mut().iter_mut() {
+            if item.guid() == nic.guid() {
+                *item = nic.clone();
+            }
+        }
+        Ok(String::new())
+    }
+}
+
+impl Backend for FakeBackend {
+    fn adapters(&self) -> Vec<Nic> {
+        self.adapters.borrow().clone()
+    }
+
+    fn apply(&self, nic: &Nic) -> Result<String> {
+        self.set(nic)
+    }
+
+    fn restore(&self, nic: &Nic) -> Result<String> {
+        let msg = self.set(nic)?;
+        self.restored.borrow_mut().push(nic.clone());
+        Ok(msg)
+    }
+
+    fn hardware(&self) -> HardwareQuery {
+        let hardware = self.hardware.borrow().clone();
+        Box::new(move || hardware)
+    }
+}
+
+/// An empty directory of its own in the temporary one, removed when dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `name` must differ between the tests, they run at the same time
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("cfg-ip-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    pub fn join(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.0.join(name)
+    }
+
+    /// the names of the files in the directory, sorted
+    pub fn files(&self) -> Vec<String> {
+        let mut files = std::fs::read_dir(&self.0)
+            .unwrap()
+            .map(|item| item.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Static settings without any address, until they're added
+pub struct NicBuilder {
+    name: String,
+    guid: String,
+    index: u32,
+    dhcp_on: bool,
+    address: Vec<Address>,
+    gateway: Vec<IpAddr>,
+    dns: Vec<IpAddr>,
+}
+
+/// the adapter `name`, like `nic("Ethernet", "{eth}").ip("192.168.1.10/24").build()`
+pub fn nic(name: &str, guid: &str) -> NicBuilder {
+    NicBuilder {
+        name: name.to_string(),
+        guid: guid.to_string(),
+        index: 1,
+        dhcp_on: false,
+        address: Vec::new(),
+        gateway: Vec::new(),
+        dns: Vec::new(),
+    }
+}
+
+impl NicBuilder {
+    pub fn index(mut self, index: u32) -> Self {
+        self.index = index;
+        self
+    }
+
+    pub fn dhcp(mut self) -> Self {
+        self.dhcp_on = true;
+        self
+    }
+
+    /// add an address like `192.168.1.10/24`
+    pub fn ip(mut self, text: &str) -> Self {
+        let (ip, prefix) = cidr::parse_cidr(text).unwrap();
+        let netmask = cidr::prefix_to_netmask(prefix, ip.is_ipv4()).unwrap();
+        self.address.push(Address { ip, netmask });
+        self
+    }
+
+    pub fn gateway(mut self, ip: &str) -> Self {
+        self.gateway.push(ip.parse().unwrap());
+        self
+    }
+
+    pub fn dns(mut self, ip: &str) -> Self {
+        self.dns.push(ip.parse().unwrap());
+        self
+    }
+
+    pub fn build(self) -> Nic {
+        Nic::new(
+            &self.name,
+            self.index,
+            &self.guid,
+            self.dhcp_on,
+            Some(self.address),
+            Some(self.gateway),
+            Some(self.dns),
+        )
+        .unwrap()
+    }
+}
@@ -0,0 +1,312 @@
+//! Exchange files for sharing saved profiles between users and machines.
+//!
+//! The file is JSON when its extension is `.json`, and YAML otherwise:
+//!
+//! ```yaml
+//! format: cfg-ip-profiles
+//! version: 1
+//! profiles:
+//!   - name: Office
+//!     adapter:
+//!       guid: "{6B29FC40-CA47-1067-B31D-00DD010662DA}"
+//!       name: Ethernet
+//!       mac: 00-1A-2B-3C-4D-5E
+//!       name_pattern: Ethernet*
+//!     group: Site A
+//!     dhcp: false
+//!     address:
+//!       - ip: 192.168.3.55
+//!         netmask: 255.255.255.0
+//!     gateway: [192.168.3.1]
+//!     dns: [192.168.1.1, 114.114.114.114]
+//! ```
+//!
+//! A template has a `template` of `ip`, `netmask`, `gateway` and `dns` texts with variables
+//! like `{site}`, one address per line, see `crate::template`.
+//!
+//! `mac`, `group` and `template` may be omitted, `name_pattern` defaults to the adapter name,
+//! and `address`, `gateway` and `dns` default to empty lists.
+//! `version` is increased whenever the layout changes, files of a newer version are refused.
+
+use crate::binding::AdapterBinding;
+use crate::store::Profile;
+use crate::template::Template;
+use crate::{Error, Result};
+use net_adapters::adapter::{Address, Nic};
+use serde_derive::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::path::Path;
+
+pub const FORMAT_NAME: &str = "cfg-ip-profiles";
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileFile {
+    format: String,
+    version: u32,
+    profiles: Vec<ProfileRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileRecord {
+    name: String,
+    adapter: AdapterRecord,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    dhcp: bool,
+    #[serde(default)]
+    address: Vec<AddressRecord>,
+    #[serde(default)]
+    gateway: Vec<IpAddr>,
+    #[serde(default)]
+    dns: Vec<IpAddr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    template: Option<Template>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AdapterRecord {
+    guid: String,
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mac: Option<String>,
+    #[serde(default)]
+    name_pattern: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AddressRecord {
+    ip: IpAddr,
+    netmask: IpAddr,
+}
+
+impl From<&Profile> for ProfileRecord {
+    fn from(value: &Profile) -> Self {
+        let nic = value.nic();
+        ProfileRecord {
+            name: value.name().to_string(),
+            adapter: AdapterRecord {
+                guid: nic.guid().to_string(),
+                name: nic.name().to_string(),
+                mac: value.binding().mac.clone(),
+                name_pattern: Some(value.binding().name_pattern.clone()),
+            },
+            group: value.group().map(str::to_string),
+            dhcp: nic.dhcp_on(),
+            address: nic
+                .address()
+                .iter()
+                .map(|item| AddressRecord {
+                    ip: item.ip,
+                    netmask: item.netmask,
+                })
+                .collect(),
+            gateway: nic.gateway().to_vec(),
+            dns: nic.dns().to_vec(),
+            template: value.template().cloned(),
+        }
+    }
+}
+
+impl ProfileRecord {
+    /// the profile of the record, read from `path`
+    fn into_profile(self, path: &Path) -> Result<Profile> {
+        let address = self
+            .address
+            .into_iter()
+            .map(|item| Address {
+                ip: item.ip,
+                netmask: item.netmask,
+            })
+            .collect();
+        // the adapter index is only valid on the machine it comes from
+        let nic = match self.dhcp {
+            true => Nic::new(
+                &self.adapter.name,
+                0,
+                &self.adapter.guid,
+                true,
+                None,
+                None,
+                None,
+            ),
+            false => Nic::new(
+                &self.adapter.name,
+                0,
+                &self.adapter.guid,
+                false,
+                Some(address),
+                Some(self.gateway),
+                Some(self.dns),
+            ),
+        }
+        .map_err(|err| Error::format(path)(format!("the profile `{}`: {}", self.name, err)))?;
+        let mut binding = AdapterBinding::new(&self.adapter.name, self.adapter.mac);
+        if let Some(pattern) = self.adapter.name_pattern {
+            binding.name_pattern = pattern;
+        }
+
+        let mut profile = Profile::with_binding(&self.name, nic, binding);
+        profile.set_group(self.group.as_deref());
+        profile.set_template(self.template);
+        Ok(profile)
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+fn profile_file<'a>(profiles: impl IntoIterator<Item = &'a Profile>) -> ProfileFile {
+    ProfileFile {
+        format: FORMAT_NAME.to_string(),
+        version: FORMAT_VERSION,
+        profiles: profiles.into_iter().map(ProfileRecord::from).collect(),
+    }
+}
+
+/// write `profiles` to `path`, see the module document for the format
+pub fn export_profiles<'a>(
+    path: impl AsRef<Path>,
+    profiles: impl IntoIterator<Item = &'a Profile>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let file = profile_file(profiles);
+    let text = match is_json(path) {
+        true => serde_json::to_string_pretty(&file).map_err(Error::format(path))?,
+        false => serde_yaml::to_string(&file).map_err(Error::format(path))?,
+    };
+    std::fs::write(path, text).map_err(Error::store(path))?;
+
+    Ok(())
+}
+
+/// the JSON of `profiles` in the export format, for other tools
+pub fn profiles_to_json<'a>(profiles: impl IntoIterator<Item = &'a Profile>) -> Result<String> {
+    crate::json::to_string(&profile_file(profiles))
+}
+
+/// read the profiles in `path`, written by `export_profiles` or by hand
+pub fn import_profiles(path: impl AsRef<Path>) -> Result<Vec<Profile>> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path).map_err(Error::store(path))?;
+    let file: ProfileFile = match is_json(path) {
+        true => serde_json::from_str(&text).map_err(Error::format(path))?,
+        false => serde_yaml::from_str(&text).map_err(Error::format(path))?,
+    };
+    if file.format != FORMAT_NAME {
+        return Err(Error::format(path)("it is not a profile file"));
+    }
+    if file.version > FORMAT_VERSION {
+        return Err(Error::format(path)(format!(
+            "it is made by a newer version of the app, format version {}",
+            file.version
+        )));
+    }
+
+    file.profiles
+        .into_iter()
+        .map(|record| record.into_profile(path))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{ConflictPolicy, IpConfigList};
+    use crate::test_support::{nic, TempDir};
+
+    fn profiles() -> Vec<Profile> {
+        let ethernet = nic("Ethernet", "{eth}")
+            .index(7)
+            .ip("192.168.3.55/24")
+            .gateway("192.168.3.1")
+            .dns("8.8.8.8")
+            .build();
+        let mut office = Profile::new("Office", ethernet, Some("00:1a:2b:3c:4d:5e".to_string()));
+        office.set_group(Some("Site A"));
+
+        let wifi = nic("Wi-Fi", "{wifi}").index(3).dhcp().build();
+        let mut site = Profile::new("Site", wifi, None);
+        site.set_template(Some(
+            Template::from_texts(
+                &["10.{site}.0.5", "255.255.0.0", "10.{site}.0.1", ""].map(String::from),
+            )
+            .unwrap(),
+        ));
+        vec![office, site]
+    }
+
+    fn round_trip(name: &str) {
+        let dir = TempDir::new(&format!("transfer-{}", name));
+        let file = dir.join(name);
+        let exported = profiles();
+        export_profiles(&file, &exported).unwrap();
+        let imported = import_profiles(&file).unwrap();
+        assert_eq!(imported.len(), exported.len());
+        for (a, b) in exported.iter().zip(&imported) {
+            assert_eq!(a.name(), b.name());
+            assert_eq!(a.group(), b.group());
+            assert_eq!(a.binding(), b.binding());
+            assert_eq!(a.template(), b.template());
+            assert_eq!(a.nic().guid(), b.nic().guid());
+            assert_eq!(a.nic().dhcp_on(), b.nic().dhcp_on());
+            assert_eq!(a.nic().address(), b.nic().address());
+            assert_eq!(a.nic().gateway(), b.nic().gateway());
+            assert_eq!(a.nic().dns(), b.nic().dns());
+        }
+
+        // the imported profiles are the saved ones
+        let mut list = IpConfigList::default();
+        list.import(exported, ConflictPolicy::Merge);
+        let report = list.import(imported, ConflictPolicy::Merge);
+        assert_eq!(report.skipped, 2);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip("profiles.json");
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        round_trip("profiles.yml");
+    }
+
+    #[test]
+    fn refuse_other_formats_and_newer_versions() {
+        let dir = TempDir::new("transfer-other");
+        let file = dir.join("other.yml");
+        std::fs::write(&file, "format: something-else\nversion: 1\nprofiles: []\n").unwrap();
+        let err = import_profiles(&file).unwrap_err();
+        assert!(matches!(err, Error::Format { .. }));
+        assert!(err.to_string().contains("is not a profile file"), "{}", err);
+
+        std::fs::write(&file, "format: cfg-ip-profiles\nversion: 2\nprofiles: []\n").unwrap();
+        let err = import_profiles(&file).unwrap_err();
+        assert!(err.to_string().contains("newer version"), "{}", err);
+    }
+
+    #[test]
+    fn defaults_of_a_hand_written_file() {
+        let dir = TempDir::new("transfer-hand");
+        let file = dir.join("hand.yml");
+        let text = "format: cfg-ip-profiles
+version: 1
+profiles:
+  - name: Home
+    adapter:
+      guid: \"{wifi}\"
+      name: Wi-Fi
+    dhcp: true
+";
+        std::fs::write(&file, text).unwrap();
+        let imported = import_profiles(&file).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].binding().name_pattern, "Wi-Fi");
+        assert_eq!(imported[0].binding().mac, None);
+        assert!(imported[0].nic().dhcp_on());
+        assert_eq!(imported[0].group(), None);
+    }
+}
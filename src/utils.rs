@@ -1,3 +1,7 @@
+use crate::binding::Hardware;
+use crate::cidr;
+use crate::form::ProfileForm;
+use crate::media::{self, MediaType};
 use crate::ui::generated_code::{
     InterfaceItemCheck, IpV4, Main, NetAddress, NetInterfaceItem, NetItemUtils,
 };
@@ -31,20 +35,42 @@ where
     slint::ModelRc::from(the_model)
 }
 
-pub fn convert(nic: &Nic) -> NetInterfaceItem {
+/// `hardware` is the card of the adapter, if it's known
+pub fn convert(nic: &Nic, hardware: Option<&Hardware>) -> NetInterfaceItem {
+    let media_type = MediaType::of(nic.name(), hardware);
     NetInterfaceItem {
         address: create_model_vec(nic.address().iter().map(convert_address).collect_vec()),
         dns: create_model_vec(nic.dns().iter().map(convert_ip).collect_vec()),
         gateway: create_model_vec(nic.gateway().iter().map(convert_ip).collect_vec()),
         guid: nic.guid().into(),
-        index: nic.index().unwrap() as i32,
+        index: nic.index().unwrap_or_default() as i32,
         is_up: nic.is_up(),
         name: nic.name().into(),
+        profile: SharedString::default(),
         dhcp_server: match nic.dhcp_server() {
             Some(s) => convert_ip(s),
             None => IpV4::default(),
         },
         dhcp_on: nic.dhcp_on(),
+        template: ModelRc::default(),
+        mac: hardware
+            .map(|item| item.mac.as_str())
+            .unwrap_or_default()
+            .into(),
+        description: hardware
+            .map(|item| item.description.as_str())
+            .unwrap_or_default()
+            .into(),
+        media_type: media_type.name().into(),
+        link_speed: hardware
+            .and_then(|item| item.link_speed)
+            .map(media::format_link_speed)
+            .unwrap_or_default()
+            .into(),
+        status: hardware
+            .and_then(|item| item.status.as_deref())
+            .unwrap_or_default()
+            .into(),
     }
 }
 
@@ -74,6 +100,24 @@ pub fn set_item_convert(window: &Main) {
         }
     });
 
+    window
+        .global::<NetItemUtils>()
+        .on_get_cidr(|address| format_cidr(&address).unwrap_or_default().into());
+
+    window.global::<NetItemUtils>().on_get_cidr_list({
+        move |net_address| {
+            let combined = net_address
+                .iter()
+                .map(|item| format_cidr(&item).unwrap_or_default())
+                .join("\n");
+            combined.into()
+        }
+    });
+
+    window
+        .global::<NetItemUtils>()
+        .on_fits_cidr(|net_address| net_address.iter().all(|item| format_cidr(&item).is_some()));
+
     window.global::<NetItemUtils>().on_get_netmask_list({
         move |net_address| {
             let combined = net_address
@@ -105,35 +149,20 @@ pub fn set_item_convert(window: &Main) {
     });
 }
 
-pub fn convert_ip_items(texts: &VecModel<SharedString>) -> anyhow::Result<Vec<Vec<IpAddr>>> {
-    // texts format： ip, netmask, gateway, dns
-    assert_eq!(texts.row_count(), 4);
-    let (ip, netmask, gateway, dns) = texts
-        .iter()
-        .map(|t| t.to_string())
-        .collect_tuple()
-        .ok_or(anyhow::Error::msg("the ui elements num error"))?;
-    let ip: Result<Vec<_>, _> = ip.lines().map(|t| t.parse::<IpAddr>()).collect();
-    if ip.is_err() {
-        return Err(anyhow::Error::msg("ip list error"));
-    }
-    let netmask: Result<Vec<_>, _> = netmask.lines().map(|t| t.parse::<IpAddr>()).collect();
-    if netmask.is_err() {
-        return Err(anyhow::Error::msg("netmask list error"));
-    }
-    let gateway: Result<Vec<_>, _> = gateway.lines().map(|t| t.parse::<IpAddr>()).collect();
-    if gateway.is_err() {
-        return Err(anyhow::Error::msg("gateway list error"));
-    }
-    let dns: Result<Vec<_>, _> = dns.lines().map(|t| t.parse::<IpAddr>()).collect();
-    if dns.is_err() {
-        return Err(anyhow::Error::msg("dns list error"));
-    }
-    let (ip, netmask) = (ip.unwrap(), netmask.unwrap());
-    if ip.len() != netmask.len() {
-        return Err(anyhow::Error::msg("length of ip and netmask don't match"));
-    }
-    let (gateway, dns) = (gateway.unwrap(), dns.unwrap());
+/// `192.168.3.55/24`, `None` if the texts are not addresses or the netmask has no prefix
+fn format_cidr(address: &NetAddress) -> Option<String> {
+    let ip = address.ip.ip.parse::<IpAddr>().ok()?;
+    let netmask = address.netmask.ip.parse::<IpAddr>().ok()?;
+    cidr::format_cidr(&ip, &netmask)
+}
+
+/// the form of the texts of the edit pane, `ip, netmask, gateway, dns`
+pub fn form_of(dhcp_on: bool, texts: &impl Model<Data = SharedString>) -> ProfileForm {
+    let texts = texts.iter().map(|text| text.to_string()).collect_vec();
+    ProfileForm::from_texts(dhcp_on, &texts)
+}
 
-    Ok(vec![ip, netmask, gateway, dns])
+/// the texts of `form` for the edit pane
+pub fn texts_of_form(form: &ProfileForm) -> ModelRc<SharedString> {
+    create_model_vec(form.texts().map(SharedString::from).to_vec())
 }
@@ -0,0 +1,493 @@
+//! Checks of the addresses in the edit pane.
+//!
+//! An error tells the field and the line that's wrong, so the UI can point at it.
+
+use crate::cidr::{self, netmask_to_prefix};
+use crate::subnet::Subnet;
+use std::fmt;
+use std::net::IpAddr;
+
+/// The boxes of the edit pane, in the order of the texts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Ip,
+    Netmask,
+    Gateway,
+    Dns,
+}
+
+impl Field {
+    pub const ALL: [Field; 4] = [Field::Ip, Field::Netmask, Field::Gateway, Field::Dns];
+
+    /// the position in the texts `ip, netmask, gateway, dns`
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Ip => "ip",
+            Field::Netmask => "netmask",
+            Field::Gateway => "gateway",
+            Field::Dns => "dns",
+        }
+    }
+}
+
+/// What's wrong with a line of a field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: Field,
+    /// starting at 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: Field, line: usize, message: impl Into<String>) -> Self {
+        FieldError {
+            field,
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} line {}: {}",
+            self.field.name(),
+            self.line,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+/// An address and the line it's on
+pub type Line = (usize, IpAddr);
+
+/// parse each line of `text` as an address, blank lines are skipped
+pub fn parse_lines(field: Field, text: &str) -> Result<Vec<Line>, FieldError> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| {
+            line.parse::<IpAddr>()
+                .map(|ip| (i, ip))
+                .map_err(|_| FieldError::new(field, i, format!("`{}` is not an address", line)))
+        })
+        .collect()
+}
+
+/// parse the ip and the netmask boxes, and pair each address with its netmask.
+/// An ip line like `192.168.3.55/24` takes its netmask from the prefix, the other
+/// ip lines take the lines of the netmask box in order
+pub fn parse_addresses(ip: &str, netmask: &str) -> Result<(Vec<Line>, Vec<Line>), FieldError> {
+    let mut netmask = parse_lines(Field::Netmask, netmask)?.into_iter();
+    let mut ips = Vec::new();
+    let mut masks = Vec::new();
+    for (i, line) in ip.lines().enumerate() {
+        let (i, line) = (i + 1, line.trim());
+        if line.is_empty() {
+            continue;
+        }
+        if line.contains('/') {
+            let (address, prefix) = cidr::parse_cidr(line)
+                .filter(|(_, prefix)| *prefix > 0)
+                .ok_or_else(|| {
+                    FieldError::new(
+                        Field::Ip,
+                        i,
+                        format!("`{}` is not an address with a prefix like /24", line),
+                    )
+                })?;
+            let mask = cidr::prefix_to_netmask(prefix, address.is_ipv4()).unwrap();
+            ips.push((i, address));
+            masks.push((i, mask));
+            continue;
+        }
+        let address = line
+            .parse::<IpAddr>()
+            .map_err(|_| FieldError::new(Field::Ip, i, format!("`{}` is not an address", line)))?;
+        let mask = netmask.next().ok_or_else(|| {
+            FieldError::new(
+                Field::Ip,
+                i,
+                "each ip needs a netmask in the same order, or a prefix like /24",
+            )
+        })?;
+        ips.push((i, address));
+        masks.push(mask);
+    }
+    if let Some((line, _)) = netmask.next() {
+        return Err(FieldError::new(
+            Field::Netmask,
+            line,
+            "there's no ip for this netmask",
+        ));
+    }
+    Ok((ips, masks))
+}
+
+/// the byte range of `line` in `text`, starting at 1, to select it in the UI
+pub fn line_offsets(text: &str, line: usize) -> (usize, usize) {
+    let mut start = 0;
+    for (i, item) in text.split('\n').enumerate() {
+        let end = start + item.trim_end_matches('\r').len();
+        if i + 1 == line {
+            return (start, end);
+        }
+        start += item.len() + 1;
+    }
+    (text.len(), text.len())
+}
+
+/// why `ip` can't be set as an address of the adapter
+fn special_kind(ip: &IpAddr) -> Option<&'static str> {
+    match ip {
+        _ if ip.is_unspecified() => Some("an unspecified"),
+        _ if ip.is_loopback() => Some("a loopback"),
+        _ if ip.is_multicast() => Some("a multicast"),
+        IpAddr::V4(ip) if ip.is_link_local() => Some("a link-local"),
+        IpAddr::V4(ip) if ip.is_broadcast() => Some("the broadcast"),
+        IpAddr::V6(ip) if ip.segments()[0] & 0xffc0 == 0xfe80 => Some("a link-local"),
+        _ => None,
+    }
+}
+
+fn is_v4(ip: &IpAddr) -> bool {
+    matches!(ip, IpAddr::V4(_))
+}
+
+/// check the parsed lines of the edit pane for static settings, see `parse_addresses` and
+/// `parse_lines`. The first error found is returned
+pub fn check(
+    ip: &[Line],
+    netmask: &[Line],
+    gateway: &[Line],
+    dns: &[Line],
+) -> Result<(), FieldError> {
+    if ip.is_empty() {
+        return Err(FieldError::new(
+            Field::Ip,
+            1,
+            "a static setting needs an address, or turn DHCP on",
+        ));
+    }
+    for (i, (line, mask)) in netmask.iter().enumerate() {
+        if netmask_to_prefix(mask).is_none_or(|len| len == 0) {
+            return Err(FieldError::new(
+                Field::Netmask,
+                *line,
+                format!(
+                    "{} is not a valid netmask, the ones must be contiguous",
+                    mask
+                ),
+            ));
+        }
+        if let Some((_, address)) = ip.get(i) {
+            if is_v4(address) != is_v4(mask) {
+                return Err(FieldError::new(
+                    Field::Netmask,
+                    *line,
+                    format!("{} doesn't fit the address {}", mask, address),
+                ));
+            }
+        }
+    }
+    if ip.len() != netmask.len() {
+        let (field, line) = match ip.len() > netmask.len() {
+            true => (Field::Ip, ip[netmask.len()].0),
+            false => (Field::Netmask, netmask[ip.len()].0),
+        };
+        return Err(FieldError::new(
+            field,
+            line,
+            "each ip needs a netmask, in the same order",
+        ));
+    }
+
+    for (i, ((line, address), (_, mask))) in ip.iter().zip(netmask).enumerate() {
+        if let Some(kind) = special_kind(address) {
+            return Err(FieldError::new(
+                Field::Ip,
+                *line,
+                format!("{} is {} address", address, kind),
+            ));
+        }
+        if let Some((other, _)) = ip[..i].iter().find(|(_, item)| item == address) {
+            return Err(FieldError::new(
+                Field::Ip,
+                *line,
+                format!("{} is already on line {}", address, other),
+            ));
+        }
+        if let (IpAddr::V4(v4), Some(len)) = (address, netmask_to_prefix(mask)) {
+            // /31 and /32 have no network or broadcast address
+            if len <= 30 {
+                let bits = u32::from(*v4);
+                let host_mask = u32::MAX >> len;
+                if bits & host_mask == 0 {
+                    return Err(FieldError::new(
+                        Field::Ip,
+                        *line,
+                        format!("{} is the network address of {}/{}", address, address, len),
+                    ));
+                }
+                if bits & host_mask == host_mask {
+                    return Err(FieldError::new(
+                        Field::Ip,
+                        *line,
+                        format!(
+                            "{} is the broadcast address of its subnet /{}",
+                            address, len
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    for (i, (line, item)) in gateway.iter().enumerate() {
+        if let Some(kind) = special_kind(item) {
+            return Err(FieldError::new(
+                Field::Gateway,
+                *line,
+                format!("{} is {} address", item, kind),
+            ));
+        }
+        if let Some((other, _)) = gateway[..i].iter().find(|(_, other)| other == item) {
+            return Err(FieldError::new(
+                Field::Gateway,
+                *line,
+                format!("{} is already on line {}", item, other),
+            ));
+        }
+        if ip.iter().any(|(_, address)| address == item) {
+            return Err(FieldError::new(
+                Field::Gateway,
+                *line,
+                format!("{} is an address of the adapter itself", item),
+            ));
+        }
+        let inside = ip
+            .iter()
+            .zip(netmask)
+            .filter_map(|((_, address), (_, mask))| Subnet::new(*address, netmask_to_prefix(mask)?))
+            .any(|subnet| subnet.contains(item));
+        if !inside {
+            return Err(FieldError::new(
+                Field::Gateway,
+                *line,
+                format!("{} is outside every subnet of the addresses", item),
+            ));
+        }
+    }
+
+    for (i, (line, item)) in dns.iter().enumerate() {
+        // a local resolver on the loopback address is fine
+        if item.is_unspecified() || item.is_multicast() {
+            return Err(FieldError::new(
+                Field::Dns,
+                *line,
+                format!("{} can't be a dns server", item),
+            ));
+        }
+        if let Some((other, _)) = dns[..i].iter().find(|(_, other)| other == item) {
+            return Err(FieldError::new(
+                Field::Dns,
+                *line,
+                format!("{} is already on line {}", item, other),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// parse and check the texts of the edit pane
+    fn validate(ip: &str, netmask: &str, gateway: &str, dns: &str) -> Result<(), FieldError> {
+        let (ip, netmask) = parse_addresses(ip, netmask)?;
+        let gateway = parse_lines(Field::Gateway, gateway)?;
+        let dns = parse_lines(Field::Dns, dns)?;
+        check(&ip, &netmask, &gateway, &dns)
+    }
+
+    fn error(ip: &str, netmask: &str, gateway: &str, dns: &str) -> String {
+        validate(ip, netmask, gateway, dns).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn valid_settings() {
+        let ip = "192.168.3.55\n\n10.0.0.5/8\n192.168.4.1";
+        let netmask = "255.255.255.0\n255.255.254.0";
+        assert_eq!(
+            validate(ip, netmask, "192.168.3.1\n10.0.0.1", "127.0.0.1"),
+            Ok(())
+        );
+        assert_eq!(validate("192.168.3.255", "255.255.255.254", "", ""), Ok(()));
+        assert_eq!(validate("fd00::5/64", "", "fd00::1", "fd00::53"), Ok(()));
+    }
+
+    #[test]
+    fn static_needs_an_address() {
+        let missing = Err(FieldError::new(
+            Field::Ip,
+            1,
+            "a static setting needs an address, or turn DHCP on",
+        ));
+        assert_eq!(validate("", "", "", ""), missing);
+        assert_eq!(validate("", "", "", "8.8.8.8"), missing);
+    }
+
+    #[test]
+    fn parse_pairs_addresses_and_netmasks() {
+        let (ips, masks) =
+            parse_addresses(" 10.0.0.5/8 \n\n192.168.3.55", "\n255.255.255.0").unwrap();
+        let ip = |text: &str| text.parse::<IpAddr>().unwrap();
+        assert_eq!(ips, [(1, ip("10.0.0.5")), (3, ip("192.168.3.55"))]);
+        assert_eq!(masks, [(1, ip("255.0.0.0")), (2, ip("255.255.255.0"))]);
+
+        let wrong = |ip: &str, netmask: &str| parse_addresses(ip, netmask).unwrap_err();
+        assert_eq!(
+            wrong("192.168.3.55", "255.255.255.0\n255.255.0.0"),
+            FieldError::new(Field::Netmask, 2, "there's no ip for this netmask")
+        );
+        assert_eq!(
+            wrong("10.0.0.5/8\n192.168.3.55", ""),
+            FieldError::new(
+                Field::Ip,
+                2,
+                "each ip needs a netmask in the same order, or a prefix like /24"
+            )
+        );
+        assert_eq!(
+            wrong("10.0.0.5/0", "").to_string(),
+            "ip line 1: `10.0.0.5/0` is not an address with a prefix like /24"
+        );
+        assert_eq!(
+            wrong("10.0.0.5", "255.255.255.0\nmask").to_string(),
+            "netmask line 2: `mask` is not an address"
+        );
+        assert_eq!(
+            wrong("\n10.0.0.x", "").to_string(),
+            "ip line 2: `10.0.0.x` is not an address"
+        );
+    }
+
+    #[test]
+    fn netmasks() {
+        assert_eq!(
+            error("192.168.3.55", "255.0.255.0", "", ""),
+            "netmask line 1: 255.0.255.0 is not a valid netmask, the ones must be contiguous"
+        );
+        assert_eq!(
+            error("192.168.3.55", "0.0.0.0", "", ""),
+            "netmask line 1: 0.0.0.0 is not a valid netmask, the ones must be contiguous"
+        );
+        assert_eq!(
+            error("fd00::5", "255.255.255.0", "", ""),
+            "netmask line 1: 255.255.255.0 doesn't fit the address fd00::5"
+        );
+    }
+
+    #[test]
+    fn network_and_broadcast_addresses() {
+        assert_eq!(
+            error("192.168.3.0/24", "", "", ""),
+            "ip line 1: 192.168.3.0 is the network address of 192.168.3.0/24"
+        );
+        assert_eq!(
+            error(
+                "10.0.0.5\n192.168.3.127",
+                "255.0.0.0\n255.255.255.128",
+                "",
+                ""
+            ),
+            "ip line 2: 192.168.3.127 is the broadcast address of its subnet /25"
+        );
+        // /31 and /32 use every address
+        assert_eq!(
+            validate("192.168.3.0/31\n192.168.4.0/32", "", "", ""),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn special_and_duplicate_addresses() {
+        let cases = [
+            ("0.0.0.0/8", "0.0.0.0 is an unspecified address"),
+            ("127.0.0.5/8", "127.0.0.5 is a loopback address"),
+            ("224.0.0.5/4", "224.0.0.5 is a multicast address"),
+            ("169.254.3.5/16", "169.254.3.5 is a link-local address"),
+            ("fe80::5/64", "fe80::5 is a link-local address"),
+        ];
+        for (ip, message) in cases {
+            assert_eq!(error(ip, "", "", ""), format!("ip line 1: {}", message));
+        }
+        assert_eq!(
+            error("10.0.0.5/8\n192.168.3.55/24\n10.0.0.5/16", "", "", ""),
+            "ip line 3: 10.0.0.5 is already on line 1"
+        );
+    }
+
+    #[test]
+    fn gateways() {
+        let ip = "192.168.3.55/24\n10.0.0.5/8";
+        assert_eq!(validate(ip, "", "10.20.30.40", ""), Ok(()));
+        assert_eq!(
+            error(ip, "", "10.0.0.1\n192.168.4.1", ""),
+            "gateway line 2: 192.168.4.1 is outside every subnet of the addresses"
+        );
+        assert_eq!(
+            error(ip, "", "192.168.3.55", ""),
+            "gateway line 1: 192.168.3.55 is an address of the adapter itself"
+        );
+        assert_eq!(
+            error(ip, "", "10.0.0.1\n\n10.0.0.1", ""),
+            "gateway line 3: 10.0.0.1 is already on line 1"
+        );
+        assert_eq!(
+            error(ip, "", "224.0.0.1", ""),
+            "gateway line 1: 224.0.0.1 is a multicast address"
+        );
+        assert_eq!(
+            error("fd00::5/64", "", "192.168.3.1", ""),
+            "gateway line 1: 192.168.3.1 is outside every subnet of the addresses"
+        );
+    }
+
+    #[test]
+    fn dns_servers() {
+        let ip = "192.168.3.5/24";
+        assert_eq!(
+            error(ip, "", "", "8.8.8.8\n0.0.0.0"),
+            "dns line 2: 0.0.0.0 can't be a dns server"
+        );
+        assert_eq!(
+            error(ip, "", "", "ff02::1"),
+            "dns line 1: ff02::1 can't be a dns server"
+        );
+        assert_eq!(
+            error(ip, "", "", "8.8.8.8\n1.1.1.1\n8.8.8.8"),
+            "dns line 3: 8.8.8.8 is already on line 1"
+        );
+    }
+
+    #[test]
+    fn line_offsets_of_the_error() {
+        let text = "10.0.0.5\r\n192.168.3.55\n";
+        assert_eq!(line_offsets(text, 1), (0, 8));
+        assert_eq!(line_offsets(text, 2), (10, 22));
+        assert_eq!(line_offsets(text, 5), (text.len(), text.len()));
+    }
+}
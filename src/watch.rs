@@ -0,0 +1,232 @@
+//! Notice when the adapters change, like a USB adapter plugged in or an address applied.
+//!
+//! On Windows the system notifies the changes of the interfaces and their addresses, and the
+//! adapters are read again only then, or once in a while in case a notification is missed.
+//! Elsewhere they are read on every poll, and `diff` tells what changed.
+
+use net_adapters::adapter::Nic;
+use std::fmt;
+
+/// the polls without a notification before the adapters are read anyway
+const FALLBACK_POLLS: u32 = 15;
+
+/// The adapters added, removed and changed, by name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// `USB added; Wi-Fi, Ethernet changed`
+impl fmt::Display for Changes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [
+            (&self.added, "added"),
+            (&self.removed, "removed"),
+            (&self.changed, "changed"),
+        ];
+        let text = parts
+            .iter()
+            .filter(|(names, _)| !names.is_empty())
+            .map(|(names, what)| format!("{} {}", names.join(", "), what))
+            .collect::<Vec<_>>()
+            .join("; ");
+        f.write_str(&text)
+    }
+}
+
+/// what changed from `old` to `new`, the adapters are matched by guid
+pub fn diff(old: &[Nic], new: &[Nic]) -> Changes {
+    let find = |nics: &[Nic], guid: &str| nics.iter().find(|item| item.guid() == guid).cloned();
+    let mut changes = Changes::default();
+    for nic in new {
+        match find(old, nic.guid()) {
+            None => changes.added.push(nic.name().to_string()),
+            Some(item) if item != *nic => changes.changed.push(nic.name().to_string()),
+            Some(_) => {}
+        }
+    }
+    changes.removed = old
+        .iter()
+        .filter(|item| find(new, item.guid()).is_none())
+        .map(|item| item.name().to_string())
+        .collect();
+    changes
+}
+
+/// Tells when the adapters should be read again
+pub struct Notifier {
+    #[cfg(windows)]
+    registration: Option<notify::Registration>,
+    /// the polls since the adapters were last read
+    idle: u32,
+}
+
+impl Notifier {
+    /// notified by the system where it's supported, polling otherwise
+    pub fn system() -> Self {
+        Notifier {
+            #[cfg(windows)]
+            registration: notify::Registration::new(),
+            idle: 0,
+        }
+    }
+
+    /// read the adapters on every poll
+    pub fn polling() -> Self {
+        Notifier {
+            #[cfg(windows)]
+            registration: None,
+            idle: 0,
+        }
+    }
+
+    /// whether the adapters may have changed since the last call
+    pub fn due(&mut self) -> bool {
+        let due = match self.notified() {
+            Some(notified) => notified || self.idle + 1 >= FALLBACK_POLLS,
+            None => true,
+        };
+        self.idle = match due {
+            true => 0,
+            false => self.idle + 1,
+        };
+        due
+    }
+
+    /// `None` without notifications
+    fn notified(&self) -> Option<bool> {
+        #[cfg(windows)]
+        if let Some(registration) = &self.registration {
+            return Some(registration.take());
+        }
+        None
+    }
+}
+
+#[cfg(windows)]
+mod notify {
+    use std::ffi::c_void;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    const AF_UNSPEC: u16 = 0;
+
+    type Callback = unsafe extern "system" fn(*const c_void, *const c_void, i32);
+    type Register = unsafe extern "system" fn(u16, Callback, *const c_void, u8, *mut isize) -> u32;
+
+    #[link(name = "iphlpapi")]
+    extern "system" {
+        fn NotifyIpInterfaceChange(
+            family: u16,
+            callback: Callback,
+            context: *const c_void,
+            initial_notification: u8,
+            handle: *mut isize,
+        ) -> u32;
+        fn NotifyUnicastIpAddressChange(
+            family: u16,
+            callback: Callback,
+            context: *const c_void,
+            initial_notification: u8,
+            handle: *mut isize,
+        ) -> u32;
+        fn CancelMibChangeNotify2(handle: isize) -> u32;
+    }
+
+    /// called on a thread of the system, `context` is the flag of the registration
+    unsafe extern "system" fn changed(context: *const c_void, _row: *const c_void, _kind: i32) {
+        (*(context as *const AtomicBool)).store(true, Ordering::Relaxed);
+    }
+
+    /// The notifications of the interface and address changes, until it's dropped
+    pub struct Registration {
+        changed: Arc<AtomicBool>,
+        handles: Vec<isize>,
+    }
+
+    impl Registration {
+        /// `None` if the system refuses one of them
+        pub fn new() -> Option<Self> {
+            let mut registration = Registration {
+                changed: Arc::new(AtomicBool::new(false)),
+                handles: Vec::new(),
+            };
+            let context = Arc::as_ptr(&registration.changed) as *const c_void;
+            let registers: [Register; 2] = [NotifyIpInterfaceChange, NotifyUnicastIpAddressChange];
+            for register in registers {
+                let mut handle = 0;
+                // the dropped registration cancels the ones made
+                if unsafe { register(AF_UNSPEC, changed, context, 0, &mut handle) } != 0 {
+                    return None;
+                }
+                registration.handles.push(handle);
+            }
+            Some(registration)
+        }
+
+        /// whether a change was notified since the last call
+        pub fn take(&self) -> bool {
+            self.changed.swap(false, Ordering::Relaxed)
+        }
+    }
+
+    impl Drop for Registration {
+        fn drop(&mut self) {
+            // it waits for the callbacks running, so `changed` outlives them
+            for handle in self.handles.drain(..) {
+                unsafe {
+                    CancelMibChangeNotify2(handle);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::nic;
+
+    #[test]
+    fn diff_by_guid() {
+        let old = [
+            nic("Ethernet", "{eth}").ip("192.168.1.10/24").build(),
+            nic("Wi-Fi", "{wifi}").ip("10.0.0.5/24").build(),
+        ];
+        assert!(diff(&old, &old).is_empty());
+
+        let new = [
+            nic("Ethernet", "{eth}").ip("192.168.1.20/24").build(),
+            nic("USB", "{usb}").ip("172.16.0.2/24").build(),
+        ];
+        let changes = diff(&old, &new);
+        assert_eq!(changes.added, ["USB"]);
+        assert_eq!(changes.removed, ["Wi-Fi"]);
+        assert_eq!(changes.changed, ["Ethernet"]);
+        assert_eq!(
+            changes.to_string(),
+            "USB added; Wi-Fi removed; Ethernet changed"
+        );
+    }
+
+    #[test]
+    fn renamed_adapter_is_changed() {
+        let old = [nic("Ethernet 2", "{usb}").ip("172.16.0.2/24").build()];
+        let new = [nic("USB", "{usb}").ip("172.16.0.2/24").build()];
+        assert_eq!(diff(&old, &new).to_string(), "USB changed");
+    }
+
+    #[test]
+    fn polling_is_always_due() {
+        let mut notifier = Notifier::polling();
+        assert!((0..FALLBACK_POLLS * 2).all(|_| notifier.due()));
+    }
+}